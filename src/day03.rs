@@ -1,50 +1,338 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
 const TREE: char = '#';
-const ROW_LEN: usize = 31;
+const OPEN: char = '.';
+const PATH_OPEN: char = 'O';
+const PATH_TREE: char = 'X';
+
+pub type Slope = (isize, usize);
+
+const PART2_SLOPES: [Slope; 5] = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MapError {
+    Empty,
+    /// A slope with `down == 0` never leaves the first row.
+    FlatSlope(Slope),
+    Ragged {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    UnknownTile {
+        line: usize,
+        column: usize,
+        tile: char,
+    },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "map contains no rows"),
+            MapError::FlatSlope(slope) => write!(f, "slope {:?} does not move down", slope),
+            MapError::Ragged {
+                line,
+                expected,
+                found,
+            } => write!(f, "line {} is {} wide, expected {}", line, found, expected),
+            MapError::UnknownTile { line, column, tile } => {
+                write!(f, "unknown tile {:?} at {}:{}", tile, line, column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// Tree hits per slope, in the order the slopes were given.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SlopeSurvey {
+    pub hits: Vec<(Slope, usize)>,
+    pub product: usize,
+}
+
+/// How a slope fared, `path_len` counts the squares the toboggan lands on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlopeRank {
+    pub slope: Slope,
+    pub trees: usize,
+    pub path_len: usize,
+}
+
+/// A rectangular map that repeats infinitely to the left and right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TobogganMap {
+    width: usize,
+    trees: Vec<Vec<bool>>,
+}
 
+impl FromStr for TobogganMap {
+    type Err = MapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut trees = Vec::new();
+        for (line_no, line) in s
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, l)| !l.is_empty())
+        {
+            let row = line
+                .chars()
+                .enumerate()
+                .map(|(column, tile)| match tile {
+                    TREE => Ok(true),
+                    OPEN => Ok(false),
+                    tile => Err(MapError::UnknownTile {
+                        line: line_no + 1,
+                        column: column + 1,
+                        tile,
+                    }),
+                })
+                .collect::<Result<Vec<bool>, MapError>>()?;
+            if let Some(first) = trees.first().map(Vec::len) {
+                if first != row.len() {
+                    return Err(MapError::Ragged {
+                        line: line_no + 1,
+                        expected: first,
+                        found: row.len(),
+                    });
+                }
+            }
+            trees.push(row);
+        }
+        match trees.first().map(Vec::len) {
+            Some(width) if width > 0 => Ok(TobogganMap { width, trees }),
+            _ => Err(MapError::Empty),
+        }
+    }
+}
+
+impl TobogganMap {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// `column` may lie outside the map, it wraps around in both directions.
+    pub fn is_tree(&self, row: usize, column: isize) -> bool {
+        self.trees[row][column.rem_euclid(self.width as isize) as usize]
+    }
+
+    pub fn trees_on_slope(&self, slope: Slope) -> Result<usize, MapError> {
+        (0..self.height())
+            .map(|row| calc_tree_hit(self, row, slope).map(|hit| hit as usize))
+            .sum()
+    }
+
+    pub fn survey(&self, slopes: &[Slope]) -> Result<SlopeSurvey, MapError> {
+        let hits = slopes
+            .iter()
+            .map(|&slope| Ok((slope, self.trees_on_slope(slope)?)))
+            .collect::<Result<Vec<(Slope, usize)>, MapError>>()?;
+        let product = hits.iter().map(|(_, count)| count).product();
+        Ok(SlopeSurvey { hits, product })
+    }
+
+    /// Squares the toboggan lands on, top to bottom, as `(row, column)`.
+    pub fn path(
+        &self,
+        (right, down): Slope,
+    ) -> Result<impl Iterator<Item = (usize, isize)>, MapError> {
+        if down == 0 {
+            return Err(MapError::FlatSlope((right, down)));
+        }
+        Ok((0..self.height())
+            .step_by(down)
+            .map(move |row| (row, (row / down) as isize * right)))
+    }
+
+    /// Every slope in the given ranges, fewest trees first and shorter paths
+    /// before longer ones on a tie. Slopes that don't move down are skipped.
+    pub fn rank_slopes(
+        &self,
+        rights: RangeInclusive<isize>,
+        downs: RangeInclusive<usize>,
+    ) -> Vec<SlopeRank> {
+        let mut ranks: Vec<SlopeRank> = downs
+            .flat_map(|down| rights.clone().map(move |right| (right, down)))
+            .filter_map(|slope| {
+                Some(SlopeRank {
+                    slope,
+                    trees: self.trees_on_slope(slope).ok()?,
+                    path_len: self.path(slope).ok()?.count(),
+                })
+            })
+            .collect();
+        ranks.sort_by_key(|rank| (rank.trees, rank.path_len, rank.slope));
+        ranks
+    }
+
+    pub fn best_slope(
+        &self,
+        rights: RangeInclusive<isize>,
+        downs: RangeInclusive<usize>,
+    ) -> Option<SlopeRank> {
+        self.rank_slopes(rights, downs).into_iter().next()
+    }
+
+    /// Draws the map with the path on top, repeating it sideways until every
+    /// square of the path is visible.
+    pub fn render_path(&self, slope: Slope) -> Result<String, MapError> {
+        let landed: HashMap<usize, isize> = self.path(slope)?.collect();
+        let width = self.width as isize;
+        let (min_col, max_col) = landed.values().fold((0, width - 1), |(min, max), &col| {
+            (min.min(col), max.max(col))
+        });
+        let first_col = min_col.div_euclid(width) * width;
+        let last_col = (max_col.div_euclid(width) + 1) * width;
+
+        let mut out = String::new();
+        for row in 0..self.height() {
+            for col in first_col..last_col {
+                let tile = match (landed.get(&row) == Some(&col), self.is_tree(row, col)) {
+                    (true, true) => PATH_TREE,
+                    (true, false) => PATH_OPEN,
+                    (false, true) => TREE,
+                    (false, false) => OPEN,
+                };
+                out.push(tile);
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+#[aoc_generator(day3)]
+pub fn generate(input: &str) -> TobogganMap {
+    input.parse().expect("could not parse map")
+}
 
 #[aoc(day3, part1)]
-pub fn part1(input: &str) -> usize {
-    input
-        .lines()
-        .enumerate()
-        .map(|(line_no, line)| line.as_bytes()[(line_no * 3) % ROW_LEN])
-        .filter(|&t| t as char == TREE)
-        .count()
+pub fn part1(map: &TobogganMap) -> usize {
+    map.trees_on_slope((3, 1)).unwrap()
 }
 
 #[aoc(day3, part2)]
-pub fn part2(input: &str) -> u32 {
-    let res = input
-        .lines()
-        .enumerate()
-        .map(|(line_no, line)| (line_no, line.as_bytes()))
-        .map(|(line_no, line)| {
-            (
-                calc_tree_hit(line_no, line, 1, 1),
-                calc_tree_hit(line_no, line, 3, 1),
-                calc_tree_hit(line_no, line, 5, 1),
-                calc_tree_hit(line_no, line, 7, 1),
-                calc_tree_hit(line_no, line, 1, 2),
-            )
-        })
-        .fold((0, 0, 0, 0, 0), |acc, val| {
-            (
-                acc.0 + val.0,
-                acc.1 + val.1,
-                acc.2 + val.2,
-                acc.3 + val.3,
-                acc.4 + val.4,
-            )
-        });
-    res.0 * res.1 * res.2 * res.3 * res.4
+pub fn part2(map: &TobogganMap) -> usize {
+    map.survey(&PART2_SLOPES).unwrap().product
 }
 
-pub fn calc_tree_hit(line_no: usize, line: &[u8], right: usize, down: usize) -> u32 {
-    if line_no % down == 0 && // might skip lines
-        line[((line_no /  down) * right) % ROW_LEN] as char == TREE
+pub fn calc_tree_hit(
+    map: &TobogganMap,
+    line_no: usize,
+    (right, down): Slope,
+) -> Result<u32, MapError> {
+    if down == 0 {
+        return Err(MapError::FlatSlope((right, down)));
+    }
+    if line_no.is_multiple_of(down) && // might skip lines
+        map.is_tree(line_no, (line_no / down) as isize * right)
     {
-        1
+        Ok(1)
     } else {
-        0
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EXAMPLE: &str = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#";
+
+    #[test]
+    pub fn test_example() {
+        let map = generate(EXAMPLE);
+        assert_eq!(7, part1(&map));
+        assert_eq!(336, part2(&map));
+        let survey = map.survey(&PART2_SLOPES).unwrap();
+        let counts: Vec<usize> = survey.hits.iter().map(|(_, c)| *c).collect();
+        assert_eq!(vec![2, 7, 3, 4, 2], counts);
+    }
+
+    #[test]
+    pub fn test_negative_right() {
+        let map: TobogganMap = "...\n..#\n.#.".parse().unwrap();
+        assert_eq!(Ok(2), map.trees_on_slope((-1, 1)));
+        assert_eq!(Ok(0), map.trees_on_slope((1, 1)));
+    }
+
+    #[test]
+    pub fn test_rank_slopes() {
+        let map = generate(EXAMPLE);
+        let ranks = map.rank_slopes(1..=7, 1..=2);
+        assert_eq!(14, ranks.len());
+        assert!(ranks.windows(2).all(|w| w[0].trees <= w[1].trees));
+        let best = map.best_slope(1..=7, 1..=2).unwrap();
+        assert_eq!(ranks[0], best);
+        assert_eq!(Ok(best.trees), map.trees_on_slope(best.slope));
+        assert_eq!(6, map.rank_slopes(1..=3, 0..=2).len());
+    }
+
+    #[test]
+    pub fn test_render_path() {
+        let map: TobogganMap = "#..\n.#.\n..#".parse().unwrap();
+        assert_eq!("X..\n.X.\n..X\n", map.render_path((1, 1)).unwrap());
+        assert_eq!("X..#..\n.#O.#.\n..#.O#\n", map.render_path((2, 1)).unwrap());
+        assert_eq!(
+            "#..#..X..\n.#..X..#.\n..X..#..#\n",
+            map.render_path((-2, 1)).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_errors() {
+        assert_eq!(Err(MapError::Empty), "".parse::<TobogganMap>());
+        assert_eq!(
+            Err(MapError::Ragged {
+                line: 2,
+                expected: 3,
+                found: 2
+            }),
+            "...\n..".parse::<TobogganMap>()
+        );
+        assert_eq!(
+            Err(MapError::UnknownTile {
+                line: 1,
+                column: 2,
+                tile: 'x'
+            }),
+            ".x.".parse::<TobogganMap>()
+        );
+        assert_eq!(
+            Err(MapError::UnknownTile {
+                line: 3,
+                column: 2,
+                tile: 'x'
+            }),
+            "...\n\n.x.".parse::<TobogganMap>()
+        );
+        let map = generate(EXAMPLE);
+        assert_eq!(
+            Err(MapError::FlatSlope((1, 0))),
+            map.survey(&[(3, 1), (1, 0)])
+        );
+        assert_eq!(Err(MapError::FlatSlope((1, 0))), map.render_path((1, 0)));
+        assert!(map.path((1, 0)).is_err());
     }
 }
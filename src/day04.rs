@@ -1,12 +1,19 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
 use parse_display::{Display, FromStr};
-use regex::Regex;
 
-lazy_static! {
-    static ref HAIR_MATCH: Regex = Regex::new(r"^#[a-z0-9]{6}$").unwrap();
-    static ref PID_MATCH: Regex = Regex::new(r"^[0-9]{9}$").unwrap();
-}
+pub mod merge;
+pub mod report;
+pub mod schema;
+pub mod suggest;
+
+use schema::DEFAULT_SCHEMA;
 
-#[derive(Display, FromStr, PartialEq, Debug)]
+pub const KEYS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+#[derive(Display, FromStr, PartialEq, Debug, Clone)]
 #[display("{}:{content}")]
 #[allow(non_camel_case_types)]
 pub enum PassportField {
@@ -20,125 +27,229 @@ pub enum PassportField {
     cid { content: String },
 }
 
-impl PassportField {
-    fn validate(&self) -> bool {
-        match self {
-            PassportField::byr { content } => content
-                .parse::<u32>()
-                .map_or(false, |val| val >= 1920 && val <= 2002),
-            PassportField::iyr { content } => content
-                .parse::<u32>()
-                .map_or(false, |val| val >= 2010 && val <= 2020),
-            PassportField::eyr { content } => content
-                .parse::<u32>()
-                .map_or(false, |val| val >= 2020 && val <= 2030),
-            PassportField::hgt { content } => match (
-                content.get(0..content.len() - 2),
-                content.get(content.len() - 2..),
-            ) {
-                (Some(h_cm), Some("cm")) => {
-                    h_cm.parse::<u32>().map_or(false, |h| h >= 150 && h <= 193)
-                }
-                (Some(h_in), Some("in")) => {
-                    h_in.parse::<u32>().map_or(false, |h| h >= 59 && h <= 76)
-                }
-                (_, _) => false,
-            },
-            PassportField::hcl { content } => HAIR_MATCH.is_match(content),
-            PassportField::ecl { content } => {
-                content == "amb"
-                    || content == "blu"
-                    || content == "brn"
-                    || content == "gry"
-                    || content == "grn"
-                    || content == "hzl"
-                    || content == "oth"
-            }
-            PassportField::pid { content } => PID_MATCH.is_match(content),
-            PassportField::cid { content: _ } => true,
+#[derive(Display, FromStr, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Height {
+    #[display("{0}cm")]
+    Cm(u32),
+    #[display("{0}in")]
+    In(u32),
+}
+
+/// Hair color as `#` and a color code. Only the shape is checked here, which
+/// codes are valid is up to the schema.
+#[derive(Display, PartialEq, Eq, Debug, Clone)]
+#[display("{0}")]
+pub struct HairColor(String);
+
+impl FromStr for HairColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > 1 && s.starts_with('#') {
+            Ok(HairColor(s.into()))
+        } else {
+            Err(format!("{} is no hair color", s))
         }
     }
 }
 
-#[aoc_generator(day4)]
-pub fn generate(input: &str) -> Vec<Vec<PassportField>> {
-    input
-        .lines()
-        .map(|line| {
-            line.split_ascii_whitespace()
-                .map(|s| s.parse().expect("Could not parse field!"))
-                .collect::<Vec<PassportField>>()
-        })
-        .collect()
+#[derive(Display, FromStr, PartialEq, Eq, Debug, Clone, Copy)]
+#[display(style = "lowercase")]
+pub enum EyeColor {
+    Amb,
+    Blu,
+    Brn,
+    Gry,
+    Grn,
+    Hzl,
+    Oth,
 }
 
-#[aoc(day4, part1)]
-pub fn part1(input: &[Vec<PassportField>]) -> usize {
-    let mut counter = 0;
-    let mut found = (false, false, false, false, false, false, false, false);
-    for field_line in input {
-        if field_line.is_empty() {
-            if let (true, true, true, true, true, true, true, _) = found {
-                counter += 1;
-            }
-
-            found = (false, false, false, false, false, false, false, false);
+/// Passport id, kept as text so leading zeros survive. Any run of digits
+/// parses, the schema decides how many there must be.
+#[derive(Display, PartialEq, Eq, Hash, Debug, Clone)]
+#[display("{0}")]
+pub struct Pid(String);
+
+impl FromStr for Pid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(Pid(s.into()))
         } else {
-            for field in field_line {
-                match field {
-                    PassportField::byr { content: _ } => found.0 = true,
-                    PassportField::iyr { content: _ } => found.1 = true,
-                    PassportField::eyr { content: _ } => found.2 = true,
-                    PassportField::hgt { content: _ } => found.3 = true,
-                    PassportField::hcl { content: _ } => found.4 = true,
-                    PassportField::ecl { content: _ } => found.5 = true,
-                    PassportField::pid { content: _ } => found.6 = true,
-                    PassportField::cid { content: _ } => found.7 = true,
-                }
-            }
+            Err(format!("{} is no passport id", s))
         }
     }
+}
 
-    if let (true, true, true, true, true, true, true, _) = found {
-        counter += 1;
+impl PassportField {
+    pub fn key(&self) -> &'static str {
+        match self {
+            PassportField::byr { .. } => "byr",
+            PassportField::iyr { .. } => "iyr",
+            PassportField::eyr { .. } => "eyr",
+            PassportField::hgt { .. } => "hgt",
+            PassportField::hcl { .. } => "hcl",
+            PassportField::ecl { .. } => "ecl",
+            PassportField::pid { .. } => "pid",
+            PassportField::cid { .. } => "cid",
+        }
     }
 
-    counter
+    pub fn content(&self) -> &str {
+        match self {
+            PassportField::byr { content }
+            | PassportField::iyr { content }
+            | PassportField::eyr { content }
+            | PassportField::hgt { content }
+            | PassportField::hcl { content }
+            | PassportField::ecl { content }
+            | PassportField::pid { content }
+            | PassportField::cid { content } => content,
+        }
+    }
+
+    pub fn with_content(&self, content: String) -> PassportField {
+        match self {
+            PassportField::byr { .. } => PassportField::byr { content },
+            PassportField::iyr { .. } => PassportField::iyr { content },
+            PassportField::eyr { .. } => PassportField::eyr { content },
+            PassportField::hgt { .. } => PassportField::hgt { content },
+            PassportField::hcl { .. } => PassportField::hcl { content },
+            PassportField::ecl { .. } => PassportField::ecl { content },
+            PassportField::pid { .. } => PassportField::pid { content },
+            PassportField::cid { .. } => PassportField::cid { content },
+        }
+    }
+
+    /// Checks the field against the default schema.
+    pub fn validate(&self) -> bool {
+        DEFAULT_SCHEMA.validate_field(self)
+    }
 }
 
-#[aoc(day4, part2)]
-pub fn part2(input: &[Vec<PassportField>]) -> usize {
-    let mut counter = 0;
-    let mut found = (false, false, false, false, false, false, false, false);
-    for field_line in input {
-        if field_line.is_empty() {
-            if let (true, true, true, true, true, true, true, _) = found {
-                counter += 1;
-            }
-
-            found = (false, false, false, false, false, false, false, false);
-        } else {
-            for field in field_line {
-                match (field, field.validate()) {
-                    (PassportField::byr { content: _ }, true) => found.0 = true,
-                    (PassportField::iyr { content: _ }, true) => found.1 = true,
-                    (PassportField::eyr { content: _ }, true) => found.2 = true,
-                    (PassportField::hgt { content: _ }, true) => found.3 = true,
-                    (PassportField::hcl { content: _ }, true) => found.4 = true,
-                    (PassportField::ecl { content: _ }, true) => found.5 = true,
-                    (PassportField::pid { content: _ }, true) => found.6 = true,
-                    (PassportField::cid { content: _ }, true) => found.7 = true,
-                    _ => {}
-                }
-            }
+/// A single passport record. Only the first occurrence of a key counts,
+/// repeated keys end up in `duplicates` and tokens with an unknown key in
+/// `unknown`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Passport {
+    pub lines: RangeInclusive<usize>,
+    pub fields: Vec<PassportField>,
+    pub duplicates: Vec<PassportField>,
+    pub unknown: Vec<String>,
+}
+
+impl Passport {
+    fn new(line_no: usize) -> Passport {
+        Passport {
+            lines: line_no..=line_no,
+            fields: Vec::new(),
+            duplicates: Vec::new(),
+            unknown: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, token: &str) {
+        match token.parse::<PassportField>() {
+            Ok(field) if self.get(field.key()).is_some() => self.duplicates.push(field),
+            Ok(field) => self.fields.push(field),
+            Err(_) => self.unknown.push(token.into()),
         }
     }
 
-    if let (true, true, true, true, true, true, true, _) = found {
-        counter += 1;
+    pub fn get(&self, key: &str) -> Option<&PassportField> {
+        self.fields.iter().find(|field| field.key() == key)
+    }
+
+    fn typed<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|field| field.content().parse().ok())
+    }
+
+    pub fn birth_year(&self) -> Option<u32> {
+        self.typed("byr")
     }
 
-    counter
+    pub fn issue_year(&self) -> Option<u32> {
+        self.typed("iyr")
+    }
+
+    pub fn expiration_year(&self) -> Option<u32> {
+        self.typed("eyr")
+    }
+
+    pub fn height(&self) -> Option<Height> {
+        self.typed("hgt")
+    }
+
+    pub fn hair_color(&self) -> Option<HairColor> {
+        self.typed("hcl")
+    }
+
+    pub fn eye_color(&self) -> Option<EyeColor> {
+        self.typed("ecl")
+    }
+
+    pub fn pid(&self) -> Option<Pid> {
+        self.typed("pid")
+    }
+
+    pub fn country_id(&self) -> Option<&str> {
+        self.get("cid").map(PassportField::content)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        DEFAULT_SCHEMA.is_complete(self)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        DEFAULT_SCHEMA.is_valid(self)
+    }
+}
+
+/// Writes the passport back as a single line of `key:value` tokens.
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tokens: Vec<String> = self
+            .fields
+            .iter()
+            .chain(&self.duplicates)
+            .map(PassportField::to_string)
+            .chain(self.unknown.iter().cloned())
+            .collect();
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+#[aoc_generator(day4)]
+pub fn generate(input: &str) -> Vec<Passport> {
+    let mut passports = Vec::new();
+    let mut current: Option<Passport> = None;
+    for (line_idx, line) in input.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let mut tokens = line.split_ascii_whitespace().peekable();
+        if tokens.peek().is_none() {
+            passports.extend(current.take());
+            continue;
+        }
+        let passport = current.get_or_insert_with(|| Passport::new(line_no));
+        passport.lines = *passport.lines.start()..=line_no;
+        for token in tokens {
+            passport.push(token);
+        }
+    }
+    passports.extend(current);
+    passports
+}
+
+#[aoc(day4, part1)]
+pub fn part1(input: &[Passport]) -> usize {
+    input.iter().filter(|p| p.is_complete()).count()
+}
+
+#[aoc(day4, part2)]
+pub fn part2(input: &[Passport]) -> usize {
+    input.iter().filter(|p| p.is_valid()).count()
 }
 
 #[cfg(test)]
@@ -219,4 +330,41 @@ iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719
         let generated = super::generate(input);
         assert_eq!(4, super::part2(&generated));
     }
+
+    #[test]
+    pub fn test_passport_model() {
+        use super::*;
+
+        let input = "pid:087499704 hgt:74in ecl:grn iyr:2012
+eyr:2030 byr:1980 hcl:#623a2f
+
+pid:000000001 hgt:190cm foo:bar hgt:150cm
+";
+        let generated = generate(input);
+        assert_eq!(2, generated.len());
+        assert_eq!(1..=2, generated[0].lines);
+        assert_eq!(Some(Height::In(74)), generated[0].height());
+        assert_eq!(Some(EyeColor::Grn), generated[0].eye_color());
+        assert!(generated[0].is_valid());
+
+        let second = &generated[1];
+        assert_eq!(4..=4, second.lines);
+        assert_eq!("000000001", second.pid().unwrap().to_string());
+        assert_eq!(Some(Height::Cm(190)), second.height());
+        assert_eq!(
+            vec!["hgt:150cm".to_string()],
+            second
+                .duplicates
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["foo:bar".to_string()], second.unknown);
+        assert!(!second.is_complete());
+        assert_eq!(None, "5".parse::<Height>().ok());
+        assert!("3556412378".parse::<Pid>().is_ok());
+        assert!("12a".parse::<Pid>().is_err());
+        assert!("#123".parse::<HairColor>().is_ok());
+        assert!("123abc".parse::<HairColor>().is_err());
+    }
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use super::Passport;
+
+/// Records of one traveller that disagree on the value of a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub pid: String,
+    pub key: String,
+    pub values: Vec<(RangeInclusive<usize>, String)>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(lines, value)| format!("{} (lines {}-{})", value, lines.start(), lines.end()))
+            .collect();
+        write!(f, "pid {} {}: {}", self.pid, self.key, values.join(" vs "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedPassport {
+    pub passport: Passport,
+    pub sources: Vec<RangeInclusive<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub passports: Vec<MergedPassport>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Unions all records sharing a `pid`, in order of first appearance. On a
+/// conflict the first value is kept and all of them are reported. Records
+/// without a `pid` are passed through untouched.
+pub fn merge_by_pid(passports: &[Passport]) -> Merge {
+    let mut merged: Vec<MergedPassport> = Vec::new();
+    let mut by_pid: HashMap<&str, usize> = HashMap::new();
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    // where the kept value of each key of a merged record came from
+    let mut origin: HashMap<(usize, &str), RangeInclusive<usize>> = HashMap::new();
+
+    for passport in passports {
+        let pid = match passport.get("pid") {
+            Some(pid) => pid.content(),
+            None => {
+                merged.push(MergedPassport {
+                    passport: passport.clone(),
+                    sources: vec![passport.lines.clone()],
+                });
+                continue;
+            }
+        };
+        let idx = *by_pid.entry(pid).or_insert_with(|| {
+            merged.push(MergedPassport {
+                passport: Passport {
+                    lines: passport.lines.clone(),
+                    fields: Vec::new(),
+                    duplicates: Vec::new(),
+                    unknown: Vec::new(),
+                },
+                sources: Vec::new(),
+            });
+            merged.len() - 1
+        });
+        let target = &mut merged[idx];
+        target.sources.push(passport.lines.clone());
+
+        for field in passport.fields.iter().chain(&passport.duplicates) {
+            let kept = target
+                .passport
+                .get(field.key())
+                .map(|f| f.content().to_string());
+            match kept {
+                None => {
+                    origin.insert((idx, field.key()), passport.lines.clone());
+                    target.passport.fields.push(field.clone());
+                }
+                Some(kept) if kept == field.content() => {}
+                Some(kept) => {
+                    let value = (passport.lines.clone(), field.content().to_string());
+                    match conflicts
+                        .iter_mut()
+                        .find(|c| c.pid == pid && c.key == field.key())
+                    {
+                        Some(conflict) => conflict.values.push(value),
+                        None => {
+                            let first = origin[&(idx, field.key())].clone();
+                            conflicts.push(Conflict {
+                                pid: pid.into(),
+                                key: field.key().into(),
+                                values: vec![(first, kept), value],
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        for token in &passport.unknown {
+            if !target.passport.unknown.contains(token) {
+                target.passport.unknown.push(token.clone());
+            }
+        }
+    }
+
+    Merge {
+        passports: merged,
+        conflicts,
+    }
+}
+
+impl Merge {
+    /// The merged records in puzzle format, one passport per paragraph.
+    pub fn to_records(&self) -> String {
+        let records: Vec<String> = self
+            .passports
+            .iter()
+            .map(|m| m.passport.to_string())
+            .collect();
+        records.join("\n\n") + "\n"
+    }
+
+    pub fn conflict_report(&self) -> String {
+        self.conflicts.iter().map(|c| format!("{}\n", c)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day04::generate;
+
+    #[test]
+    pub fn test_merge() {
+        let input = "pid:012345678 byr:1980
+hgt:170cm
+
+ecl:brn cid:12
+
+pid:012345678 ecl:grn hgt:170cm
+
+pid:012345678 hgt:171cm foo:bar
+";
+        let merge = merge_by_pid(&generate(input));
+        assert_eq!(2, merge.passports.len());
+        assert_eq!(vec![1..=2, 6..=6, 8..=8], merge.passports[0].sources);
+        assert_eq!(
+            "pid:012345678 byr:1980 hgt:170cm ecl:grn foo:bar\n\necl:brn cid:12\n",
+            merge.to_records()
+        );
+        assert_eq!(
+            "pid 012345678 hgt: 170cm (lines 1-2) vs 171cm (lines 8-8)\n",
+            merge.conflict_report()
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use super::schema::Schema;
+use super::Passport;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub key: String,
+    pub value: String,
+    pub rule: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: {}", self.key, self.value, self.rule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassportReport {
+    pub lines: RangeInclusive<usize>,
+    pub missing: Vec<String>,
+    pub invalid: Vec<Violation>,
+}
+
+impl PassportReport {
+    pub fn new(schema: &Schema, passport: &Passport) -> PassportReport {
+        let missing = schema
+            .required()
+            .filter(|f| passport.get(&f.key).is_none())
+            .map(|f| f.key.clone())
+            .collect();
+        let invalid = passport
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let rule = schema.get(field.key())?.check.violation(field.content())?;
+                Some(Violation {
+                    key: field.key().into(),
+                    value: field.content().into(),
+                    rule,
+                })
+            })
+            .collect();
+        PassportReport {
+            lines: passport.lines.clone(),
+            missing,
+            invalid,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+/// Why each passport of a batch passed or failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub passports: Vec<PassportReport>,
+}
+
+impl ValidationReport {
+    pub fn new(schema: &Schema, passports: &[Passport]) -> ValidationReport {
+        ValidationReport {
+            passports: passports
+                .iter()
+                .map(|p| PassportReport::new(schema, p))
+                .collect(),
+        }
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &PassportReport> {
+        self.passports.iter().filter(|p| !p.is_valid())
+    }
+
+    pub fn to_json(&self) -> String {
+        let passports: Vec<String> = self
+            .passports
+            .iter()
+            .map(|p| {
+                let missing: Vec<String> = p.missing.iter().map(|k| json_str(k)).collect();
+                let invalid: Vec<String> = p
+                    .invalid
+                    .iter()
+                    .map(|v| {
+                        format!(
+                            "{{\"key\": {}, \"value\": {}, \"rule\": {}}}",
+                            json_str(&v.key),
+                            json_str(&v.value),
+                            json_str(&v.rule)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "  {{\"lines\": [{}, {}], \"valid\": {}, \"missing\": [{}], \"invalid\": [{}]}}",
+                    p.lines.start(),
+                    p.lines.end(),
+                    p.is_valid(),
+                    missing.join(", "),
+                    invalid.join(", ")
+                )
+            })
+            .collect();
+        format!("[\n{}\n]\n", passports.join(",\n"))
+    }
+
+    /// One row per passport, multiple missing keys or violations are joined
+    /// with `; `.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("first_line,last_line,valid,missing,invalid\n");
+        for p in &self.passports {
+            let invalid: Vec<String> = p.invalid.iter().map(Violation::to_string).collect();
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                p.lines.start(),
+                p.lines.end(),
+                p.is_valid(),
+                csv_str(&p.missing.join("; ")),
+                csv_str(&invalid.join("; "))
+            ));
+        }
+        out
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn csv_str(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day04::generate;
+    use crate::day04::schema::DEFAULT_SCHEMA;
+
+    const INPUT: &str = "hgt:59cm ecl:zzz
+eyr:2038 hcl:74454a iyr:2023
+pid:3556412378 byr:2007
+
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f
+
+hcl:\"x,y\" byr:1990";
+
+    #[test]
+    pub fn test_report() {
+        let report = ValidationReport::new(&DEFAULT_SCHEMA, &generate(INPUT));
+        assert_eq!(3, report.passports.len());
+        assert_eq!(2, report.failed().count());
+
+        let first = &report.passports[0];
+        assert_eq!(1..=3, first.lines);
+        assert!(first.missing.is_empty());
+        assert_eq!(7, first.invalid.len());
+        assert_eq!("hgt 59cm: cm must be 150–193", first.invalid[0].to_string());
+        assert_eq!("byr 2007: must be 1920–2002", first.invalid[6].to_string());
+
+        assert!(report.passports[1].is_valid());
+        assert_eq!(5..=6, report.passports[1].lines);
+        assert_eq!(
+            vec!["iyr", "eyr", "hgt", "ecl", "pid"],
+            report.passports[2].missing
+        );
+    }
+
+    #[test]
+    pub fn test_export() {
+        let report = ValidationReport::new(&DEFAULT_SCHEMA, &generate(INPUT));
+        let csv = report.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(4, rows.len());
+        assert_eq!("5,6,true,,", rows[2]);
+        assert!(
+            rows[3].starts_with("8,8,false,iyr; eyr; hgt; ecl; pid,\"hcl \"\"x,y\"\": must match")
+        );
+
+        let json = report.to_json();
+        assert!(
+            json.contains("{\"lines\": [5, 6], \"valid\": true, \"missing\": [], \"invalid\": []}")
+        );
+        assert!(json.contains("{\"key\": \"hcl\", \"value\": \"\\\"x,y\\\"\", \"rule\": \"must match ^#[a-z0-9]{6}$\"}"));
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::Pair;
+use pest::{Parser, Span};
+use regex::Regex;
+
+use super::{Passport, PassportField, KEYS};
+
+#[derive(Parser)]
+#[grammar = "passportschema.pest"]
+pub struct SchemaParser;
+
+lazy_static! {
+    /// The rules of the puzzle, shipped as `src/passport.schema`.
+    pub static ref DEFAULT_SCHEMA: Schema = include_str!("../passport.schema")
+        .parse()
+        .expect("default schema is malformed");
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(std::io::Error),
+    Parse(Box<Error<Rule>>),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Io(e) => write!(f, "could not read schema: {}", e),
+            SchemaError::Parse(e) => write!(f, "malformed schema:\n{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<std::io::Error> for SchemaError {
+    fn from(e: std::io::Error) -> Self {
+        SchemaError::Io(e)
+    }
+}
+
+impl From<Error<Rule>> for SchemaError {
+    fn from(e: Error<Rule>) -> Self {
+        SchemaError::Parse(Box::new(e.renamed_rules(describe_rule)))
+    }
+}
+
+fn describe_rule(rule: &Rule) -> String {
+    match rule {
+        Rule::key => "field key".into(),
+        Rule::colon => "`:` after the field key".into(),
+        Rule::presence => "`required` or `optional`".into(),
+        Rule::int_check
+        | Rule::unit_check
+        | Rule::regex_check
+        | Rule::enum_check
+        | Rule::any_check => "`int`, `unit`, `regex`, `enum` or `any`".into(),
+        Rule::range => "range like `1920-2002`".into(),
+        Rule::unit_range => "range with unit like `150-193cm`".into(),
+        Rule::word => "enum value".into(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn custom_error(span: Span, message: String) -> SchemaError {
+    Error::new_from_span(ErrorVariant::CustomError { message }, span).into()
+}
+
+#[derive(Debug, Clone)]
+pub enum Check {
+    Any,
+    Int(RangeInclusive<u64>),
+    Units(Vec<(String, RangeInclusive<u64>)>),
+    Pattern(Regex),
+    OneOf(Vec<String>),
+}
+
+impl Check {
+    pub fn accepts(&self, value: &str) -> bool {
+        self.violation(value).is_none()
+    }
+
+    /// Describes the broken rule, if `value` breaks it.
+    pub fn violation(&self, value: &str) -> Option<String> {
+        let in_range =
+            |v: &str, range: &RangeInclusive<u64>| v.parse().is_ok_and(|v| range.contains(&v));
+        match self {
+            Check::Any => None,
+            Check::Int(range) if in_range(value, range) => None,
+            Check::Int(range) => Some(format!("must be {}–{}", range.start(), range.end())),
+            Check::Units(units) => {
+                // the longest matching unit wins, so `cm` is not read as `m`
+                match units
+                    .iter()
+                    .filter_map(|(unit, range)| {
+                        Some((unit, range, value.strip_suffix(unit.as_str())?))
+                    })
+                    .max_by_key(|(unit, _, _)| unit.len())
+                {
+                    Some((_, range, v)) if in_range(v, range) => None,
+                    Some((unit, range, _)) => Some(format!(
+                        "{} must be {}–{}",
+                        unit,
+                        range.start(),
+                        range.end()
+                    )),
+                    None => Some(format!(
+                        "unit must be one of {}",
+                        units
+                            .iter()
+                            .map(|(unit, _)| unit.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                }
+            }
+            Check::Pattern(regex) if regex.is_match(value) => None,
+            Check::Pattern(regex) => Some(format!("must match {}", regex)),
+            Check::OneOf(values) if values.iter().any(|v| v == value) => None,
+            Check::OneOf(values) => Some(format!("must be one of {}", values.join(", "))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldSchema {
+    pub key: String,
+    pub required: bool,
+    pub check: Check,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub fields: Vec<FieldSchema>,
+}
+
+impl FromStr for Schema {
+    type Err = SchemaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file = SchemaParser::parse(Rule::file, s)?
+            .next()
+            .expect("file rule always matches once");
+        let mut fields: Vec<FieldSchema> = Vec::new();
+        for pair in file.into_inner() {
+            match pair.as_rule() {
+                Rule::field => {
+                    let span = pair.as_span();
+                    let field = parse_field(pair)?;
+                    if fields.iter().any(|f| f.key == field.key) {
+                        return Err(custom_error(
+                            span,
+                            format!("{} is defined more than once", field.key),
+                        ));
+                    }
+                    fields.push(field);
+                }
+                Rule::EOI => {}
+                _ => unreachable!(),
+            }
+        }
+        Ok(Schema { fields })
+    }
+}
+
+fn parse_field(pair: Pair<Rule>) -> Result<FieldSchema, SchemaError> {
+    let mut inner = pair.into_inner();
+    let key = inner.next().expect("field has a key");
+    inner.next().expect("key is followed by a colon");
+    if !KEYS.contains(&key.as_str()) {
+        return Err(custom_error(
+            key.as_span(),
+            format!(
+                "unknown field {}, expected one of {}",
+                key.as_str(),
+                KEYS.join(", ")
+            ),
+        ));
+    }
+    let required = inner.next().expect("field has a presence").as_str() == "required";
+    let check = inner.next().expect("field has a check");
+    let check = match check.as_rule() {
+        Rule::any_check => Check::Any,
+        Rule::int_check => Check::Int(parse_range(check.into_inner().next().unwrap())?),
+        Rule::unit_check => Check::Units(
+            check
+                .into_inner()
+                .map(|unit_range| {
+                    let mut inner = unit_range.into_inner();
+                    let range = parse_range(inner.next().unwrap())?;
+                    Ok((inner.next().unwrap().as_str().to_string(), range))
+                })
+                .collect::<Result<_, SchemaError>>()?,
+        ),
+        Rule::regex_check => {
+            let pattern = check.into_inner().next().unwrap();
+            Check::Pattern(
+                Regex::new(pattern.as_str().trim_end())
+                    .map_err(|e| custom_error(pattern.as_span(), e.to_string()))?,
+            )
+        }
+        Rule::enum_check => Check::OneOf(
+            check
+                .into_inner()
+                .map(|word| word.as_str().to_string())
+                .collect(),
+        ),
+        _ => unreachable!(),
+    };
+    Ok(FieldSchema {
+        key: key.as_str().into(),
+        required,
+        check,
+    })
+}
+
+fn parse_range(pair: Pair<Rule>) -> Result<RangeInclusive<u64>, SchemaError> {
+    let span = pair.as_span();
+    let mut bounds = pair.into_inner().map(|number| {
+        number
+            .as_str()
+            .parse::<u64>()
+            .map_err(|e| custom_error(number.as_span(), e.to_string()))
+    });
+    let (min, max) = (bounds.next().unwrap()?, bounds.next().unwrap()?);
+    if min > max {
+        return Err(custom_error(
+            span,
+            format!("range starts at {} but ends at {}", min, max),
+        ));
+    }
+    Ok(min..=max)
+}
+
+impl Schema {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Schema, SchemaError> {
+        let path = path.as_ref();
+        fs::read_to_string(path)?.parse().map_err(|e| match e {
+            SchemaError::Parse(e) => {
+                SchemaError::Parse(Box::new(e.with_path(&path.to_string_lossy())))
+            }
+            other => other,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|f| f.key == key)
+    }
+
+    pub fn required(&self) -> impl Iterator<Item = &FieldSchema> {
+        self.fields.iter().filter(|f| f.required)
+    }
+
+    /// Fields without a rule are accepted as is.
+    pub fn validate_field(&self, field: &PassportField) -> bool {
+        self.get(field.key())
+            .is_none_or(|schema| schema.check.accepts(field.content()))
+    }
+
+    pub fn is_complete(&self, passport: &Passport) -> bool {
+        self.required().all(|f| passport.get(&f.key).is_some())
+    }
+
+    pub fn is_valid(&self, passport: &Passport) -> bool {
+        self.is_complete(passport)
+            && passport
+                .fields
+                .iter()
+                .all(|field| self.validate_field(field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn test_default_schema() {
+        let hgt = &DEFAULT_SCHEMA.get("hgt").unwrap().check;
+        assert!(hgt.accepts("60in"));
+        assert!(hgt.accepts("190cm"));
+        assert!(!hgt.accepts("190in"));
+        assert!(!hgt.accepts("190"));
+        assert_eq!(Some("cm must be 150–193".into()), hgt.violation("59cm"));
+        assert_eq!(
+            Some("unit must be one of cm, in".into()),
+            hgt.violation("59")
+        );
+        let ecl = &DEFAULT_SCHEMA.get("ecl").unwrap().check;
+        assert!(ecl.accepts("brn"));
+        assert!(!ecl.accepts("wat"));
+        assert!(!DEFAULT_SCHEMA.get("cid").unwrap().required);
+        assert_eq!(7, DEFAULT_SCHEMA.required().count());
+    }
+
+    #[test]
+    pub fn test_overlapping_units() {
+        let schema: Schema = "hgt: required unit 1-3m 100-250cm".parse().unwrap();
+        let hgt = &schema.get("hgt").unwrap().check;
+        assert!(hgt.accepts("170cm"));
+        assert!(hgt.accepts("2m"));
+        assert_eq!(Some("cm must be 100–250".into()), hgt.violation("2cm"));
+        assert_eq!(Some("m must be 1–3".into()), hgt.violation("170m"));
+    }
+
+    #[test]
+    pub fn test_custom_schema() {
+        let schema: Schema =
+            "byr: required int 1900-1950 # old folks only\n\npid: optional regex ^0"
+                .parse()
+                .unwrap();
+        let passports = crate::day04::generate("byr:1930 pid:123\n\nbyr:1930\n\nbyr:1960 pid:012");
+        let valid: Vec<bool> = passports.iter().map(|p| schema.is_valid(p)).collect();
+        assert_eq!(vec![false, true, false], valid);
+    }
+
+    #[test]
+    pub fn test_malformed_schema() {
+        let message = |input: &str| input.parse::<Schema>().unwrap_err().to_string();
+        assert!(message("byr: mandatory int 1-2").contains("`required` or `optional`"));
+        assert!(message("byr: required int 1-2\nbyr: optional any")
+            .contains("byr is defined more than once"));
+        assert!(message("byr: required int 5-2").contains("range starts at 5 but ends at 2"));
+        assert!(message("foo: required any").contains("unknown field foo"));
+        assert!(message("byr required any").contains("expected `:` after the field key"));
+        assert!(message("hcl: required regex ^#[a-z").contains("1:21"));
+        assert!("byr:requiredint1-2".parse::<Schema>().is_err());
+        assert!("byr: required int1-2".parse::<Schema>().is_err());
+        assert!("ecl: required enum amb\tblu".parse::<Schema>().is_ok());
+    }
+}
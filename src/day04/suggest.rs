@@ -0,0 +1,245 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::schema::{Check, Schema};
+use super::Passport;
+
+/// Characters tried when inserting or replacing one.
+const ALPHABET: &str = "#0123456789abcdefghijklmnopqrstuvwxyz";
+const INDEL_COST: u32 = 1;
+const SUBSTITUTE_COST: u32 = 2;
+/// Fixes that need more than a substitution or two insertions/deletions
+/// are no longer near-misses.
+pub const MAX_EDIT_COST: u32 = 2;
+/// Longer values are not looked at, the number of double edits grows with
+/// the square of the length.
+pub const MAX_VALUE_LEN: usize = 16;
+
+/// A proposed replacement for an invalid field. `confidence` is one over the
+/// number of equally cheap edits that would have fixed the field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub key: String,
+    pub value: String,
+    pub replacement: String,
+    pub cost: u32,
+    pub confidence: f64,
+}
+
+fn indels(value: &str) -> BTreeSet<String> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut edits = BTreeSet::new();
+    for idx in 0..chars.len() {
+        let mut edit = chars.clone();
+        edit.remove(idx);
+        edits.insert(edit.into_iter().collect());
+    }
+    for idx in 0..=chars.len() {
+        for c in ALPHABET.chars() {
+            let mut edit = chars.clone();
+            edit.insert(idx, c);
+            edits.insert(edit.into_iter().collect());
+        }
+    }
+    edits
+}
+
+fn substitutions(value: &str) -> BTreeSet<String> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut edits = BTreeSet::new();
+    for idx in 0..chars.len() {
+        for c in ALPHABET.chars().filter(|&c| c != chars[idx]) {
+            let mut edit = chars.clone();
+            edit[idx] = c;
+            edits.insert(edit.into_iter().collect());
+        }
+    }
+    edits
+}
+
+/// Weighted Levenshtein distance, a substitution costs as much as deleting
+/// and inserting.
+pub fn edit_cost(from: &str, to: &str) -> u32 {
+    let to: Vec<char> = to.chars().collect();
+    let mut prev: Vec<u32> = (0..=to.len() as u32).map(|i| i * INDEL_COST).collect();
+    for (i, a) in from.chars().enumerate() {
+        let mut curr = vec![(i as u32 + 1) * INDEL_COST];
+        for (j, b) in to.iter().enumerate() {
+            let substitute = prev[j] + if a == *b { 0 } else { SUBSTITUTE_COST };
+            curr.push(
+                substitute
+                    .min(prev[j + 1] + INDEL_COST)
+                    .min(curr[j] + INDEL_COST),
+            );
+        }
+        prev = curr;
+    }
+    prev[to.len()]
+}
+
+/// All cheapest values within `MAX_EDIT_COST` of `value` that pass `check`,
+/// sorted, together with their cost. Values over `MAX_EDIT_COST` away from
+/// every length `check` could accept get none.
+pub fn cheapest_fixes(check: &Check, value: &str) -> Option<(u32, Vec<String>)> {
+    if let Check::OneOf(options) = check {
+        let cost = options.iter().map(|o| edit_cost(value, o)).min()?;
+        if cost > MAX_EDIT_COST {
+            return None;
+        }
+        let fixes = options
+            .iter()
+            .filter(|o| edit_cost(value, o) == cost)
+            .cloned()
+            .collect();
+        return Some((cost, fixes));
+    }
+    let len = value.chars().count();
+    if len > MAX_VALUE_LEN {
+        return None;
+    }
+    // leading zeros make numbers longer, never shorter
+    if let Check::Int(range) = check {
+        let max_inserts = (MAX_EDIT_COST / INDEL_COST) as usize;
+        if len + max_inserts < range.start().to_string().len() {
+            return None;
+        }
+    }
+
+    let single = indels(value);
+    let fixes: Vec<String> = single
+        .iter()
+        .filter(|e| check.accepts(e))
+        .cloned()
+        .collect();
+    if !fixes.is_empty() {
+        return Some((INDEL_COST, fixes));
+    }
+    let double: BTreeSet<String> = single
+        .iter()
+        .flat_map(|e| indels(e))
+        .chain(substitutions(value))
+        .collect();
+    let fixes: Vec<String> = double.into_iter().filter(|e| check.accepts(e)).collect();
+    if fixes.is_empty() {
+        None
+    } else {
+        Some((2 * INDEL_COST, fixes))
+    }
+}
+
+/// Suggestions for every invalid field of the passport. Missing fields are
+/// left alone, there is nothing to base a value on.
+pub fn suggest(schema: &Schema, passport: &Passport) -> Vec<Suggestion> {
+    passport
+        .fields
+        .iter()
+        .filter_map(|field| {
+            let check = &schema.get(field.key())?.check;
+            if check.accepts(field.content()) {
+                return None;
+            }
+            let (cost, fixes) = cheapest_fixes(check, field.content())?;
+            Some(Suggestion {
+                key: field.key().into(),
+                value: field.content().into(),
+                replacement: fixes[0].clone(),
+                cost,
+                confidence: 1.0 / fixes.len() as f64,
+            })
+        })
+        .collect()
+}
+
+/// A copy of the passport with every suggestion of at least `min_confidence`
+/// applied.
+pub fn apply(passport: &Passport, suggestions: &[Suggestion], min_confidence: f64) -> Passport {
+    let mut corrected = passport.clone();
+    for suggestion in suggestions
+        .iter()
+        .filter(|s| s.confidence >= min_confidence)
+    {
+        for field in corrected
+            .fields
+            .iter_mut()
+            .filter(|f| f.key() == suggestion.key && f.content() == suggestion.value)
+        {
+            *field = field.with_content(suggestion.replacement.clone());
+        }
+    }
+    corrected
+}
+
+/// The whole batch in puzzle format, one passport per paragraph.
+pub fn corrected(schema: &Schema, passports: &[Passport], min_confidence: f64) -> String {
+    let corrected: Vec<String> = passports
+        .iter()
+        .map(|p| apply(p, &suggest(schema, p), min_confidence).to_string())
+        .collect();
+    corrected.join("\n\n") + "\n"
+}
+
+pub fn write_corrected<P: AsRef<Path>>(
+    path: P,
+    schema: &Schema,
+    passports: &[Passport],
+    min_confidence: f64,
+) -> io::Result<()> {
+    fs::write(path, corrected(schema, passports, min_confidence))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day04::generate;
+    use crate::day04::schema::DEFAULT_SCHEMA;
+
+    #[test]
+    pub fn test_edit_cost() {
+        assert_eq!(0, edit_cost("gry", "gry"));
+        assert_eq!(1, edit_cost("dab227", "#dab227"));
+        assert_eq!(2, edit_cost("grx", "gry"));
+        assert_eq!(2, edit_cost("gyr", "gry"));
+        assert_eq!(6, edit_cost("zzz", "amb"));
+    }
+
+    #[test]
+    pub fn test_suggest() {
+        let passports =
+            generate("hgt:170 hcl:dab227 pid:3556412378 ecl:zzz iyr:2015 byr:1980 eyr:2025");
+        let suggestions = suggest(&DEFAULT_SCHEMA, &passports[0]);
+        assert_eq!(3, suggestions.len());
+
+        assert_eq!("170cm", suggestions[0].replacement);
+        assert_eq!(2, suggestions[0].cost);
+        assert_eq!(1.0, suggestions[0].confidence);
+
+        assert_eq!("#dab227", suggestions[1].replacement);
+        assert_eq!(1, suggestions[1].cost);
+        assert_eq!(1.0, suggestions[1].confidence);
+
+        assert_eq!("pid", suggestions[2].key);
+        assert_eq!(9, suggestions[2].replacement.len());
+        assert!(suggestions[2].confidence < 0.2);
+    }
+
+    #[test]
+    pub fn test_hopeless() {
+        let byr = &DEFAULT_SCHEMA.get("byr").unwrap().check;
+        assert_eq!(None, cheapest_fixes(byr, "1"));
+        assert!(cheapest_fixes(byr, "20").is_some());
+        let hcl = &DEFAULT_SCHEMA.get("hcl").unwrap().check;
+        assert_eq!(None, cheapest_fixes(hcl, &"a".repeat(10_000)));
+    }
+
+    #[test]
+    pub fn test_corrected() {
+        let passports = generate("hgt:170 hcl:dab227 pid:3556412378\n\nhcl:#123456 foo:bar");
+        assert_eq!(
+            "hgt:170cm hcl:#dab227 pid:3556412378\n\nhcl:#123456 foo:bar\n",
+            corrected(&DEFAULT_SCHEMA, &passports, 0.5)
+        );
+    }
+}
@@ -1,8 +1,163 @@
+use std::fmt;
+use std::str::FromStr;
 
+pub mod allocate;
+pub mod seatmap;
+
+use seatmap::SeatMap;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct BoardingPass {
+    pub row: u32,
+    pub column: u32,
     pub board_id: u32,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassError {
+    WrongLength { expected: usize, found: usize },
+    UnexpectedChar { position: usize, found: char, expected: &'static str },
+    OutOfRange { seat_id: u32, max: u32 },
+    TooManyBits { bits: u32 },
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassError::WrongLength { expected, found } => write!(
+                f,
+                "boarding pass has {} characters, expected {}",
+                found, expected
+            ),
+            PassError::UnexpectedChar {
+                position,
+                found,
+                expected,
+            } => write!(
+                f,
+                "unexpected {:?} at position {}, expected {}",
+                found, position, expected
+            ),
+            PassError::OutOfRange { seat_id, max } => {
+                write!(f, "seat {} does not exist, the last one is {}", seat_id, max)
+            }
+            PassError::TooManyBits { bits } => {
+                write!(f, "passes of {} characters give seat ids beyond u32", bits)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PassError {}
+
+/// Translates between `FBLR` passes and seats for a plane with
+/// `2^row_bits` rows of `2^column_bits` seats.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BoardingPassCodec {
+    pub row_bits: u32,
+    pub column_bits: u32,
+}
+
+impl Default for BoardingPassCodec {
+    fn default() -> Self {
+        BoardingPassCodec {
+            row_bits: 7,
+            column_bits: 3,
+        }
+    }
+}
+
+impl BoardingPassCodec {
+    /// Fails unless every seat id fits into a `u32` with room to spare.
+    pub fn new(row_bits: u32, column_bits: u32) -> Result<BoardingPassCodec, PassError> {
+        let bits = row_bits.saturating_add(column_bits);
+        if bits >= 32 {
+            return Err(PassError::TooManyBits { bits });
+        }
+        Ok(BoardingPassCodec {
+            row_bits,
+            column_bits,
+        })
+    }
+
+    pub fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    pub fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    pub fn pass_len(&self) -> usize {
+        (self.row_bits + self.column_bits) as usize
+    }
+
+    pub fn seat_id(&self, row: u32, column: u32) -> u32 {
+        row << self.column_bits | column
+    }
+
+    pub fn decode(&self, pass: &str) -> Result<BoardingPass, PassError> {
+        let found = pass.chars().count();
+        if found != self.pass_len() {
+            return Err(PassError::WrongLength {
+                expected: self.pass_len(),
+                found,
+            });
+        }
+        let mut board_id = 0;
+        for (idx, c) in pass.chars().enumerate() {
+            let is_row = idx < self.row_bits as usize;
+            let bit = match (c, is_row) {
+                ('F', true) | ('L', false) => 0,
+                ('B', true) | ('R', false) => 1,
+                (found, _) => {
+                    return Err(PassError::UnexpectedChar {
+                        position: idx,
+                        found,
+                        expected: if is_row { "F or B" } else { "L or R" },
+                    })
+                }
+            };
+            board_id = board_id << 1 | bit;
+        }
+        Ok(self.seat(board_id))
+    }
+
+    pub fn seat(&self, seat_id: u32) -> BoardingPass {
+        BoardingPass {
+            row: seat_id >> self.column_bits,
+            column: seat_id & (self.columns() - 1),
+            board_id: seat_id,
+        }
+    }
+
+    pub fn encode(&self, seat_id: u32) -> Result<String, PassError> {
+        let max = self.seat_id(self.rows() - 1, self.columns() - 1);
+        if seat_id > max {
+            return Err(PassError::OutOfRange { seat_id, max });
+        }
+        Ok((0..self.pass_len())
+            .map(|idx| {
+                let bit = seat_id >> (self.pass_len() - 1 - idx) & 1;
+                match (idx < self.row_bits as usize, bit) {
+                    (true, 0) => 'F',
+                    (true, _) => 'B',
+                    (false, 0) => 'L',
+                    (false, _) => 'R',
+                }
+            })
+            .collect())
+    }
+}
+
+impl FromStr for BoardingPass {
+    type Err = PassError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BoardingPassCodec::default().decode(s)
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct BoardingCollector {
     pub accum: u32,
@@ -26,26 +181,12 @@ impl BoardingCollector {
 
 }
 
-impl From<&str> for BoardingPass {
-
-    fn from(s: &str) -> Self {
-        let mut board_id = 0;
-        for (idx, char) in s.chars().enumerate() {
-            match char {
-                'B' | 'R' => board_id |= 1 << (9-idx), // partition is 7-idx << 3 due to *8, seating is 9-idx due to starting at 7 with len 3
-                'L' | 'F' => {},
-                u => panic!("unexpected char! {}", u),
-            }
-        }
-
-        BoardingPass { board_id }
-    }
-}
-
-
 #[aoc_generator(day5)]
 pub fn input_generator(input: &str) -> Vec<BoardingPass> {
-    input.lines().map(|s| BoardingPass::from(s)).collect()
+    input
+        .lines()
+        .map(|s| s.parse().expect("could not parse boarding pass"))
+        .collect()
 }
 
 #[aoc(day5, part1)]
@@ -55,6 +196,15 @@ pub fn part1(passes: &[BoardingPass]) -> u32 {
 
 #[aoc(day5, part2)]
 pub fn part2(passes: &[BoardingPass]) -> u32 {
+    SeatMap::new(BoardingPassCodec::default(), passes)
+        .expect("pass for a seat off the plane")
+        .my_seat()
+        .expect("could not find my seat")
+}
+
+/// Only correct if exactly one seat is missing and no pass is duplicated.
+#[aoc(day5, part2, GaussSum)]
+pub fn part2_gauss_sum(passes: &[BoardingPass]) -> u32 {
     let collect = passes.iter().map(|pass| pass.board_id).fold(BoardingCollector::new(), |accum, b| accum.fold(b));
     // add all numbers up to max
     let up_to_max = collect.max * (collect.max + 1) / 2;
@@ -72,15 +222,50 @@ pub fn part2(passes: &[BoardingPass]) -> u32 {
 
 #[cfg(test)]
 mod tests {
-    use super::BoardingPass;
+    use super::*;
 
 
     #[test]
     pub fn test_passes() {
-        let passes = vec![BoardingPass::from("BFFFBBFRRR"), BoardingPass::from("FFFBBBFRRR"), BoardingPass::from("BBFFBBFRLL")];
+        let passes = super::input_generator("BFFFBBFRRR\nFFFBBBFRRR\nBBFFBBFRLL");
         assert_eq!(820, super::part1(&passes));
     }
 
+    #[test]
+    pub fn test_codec() {
+        let codec = BoardingPassCodec::default();
+        assert_eq!(
+            Ok(BoardingPass { row: 70, column: 7, board_id: 567 }),
+            codec.decode("BFFFBBFRRR")
+        );
+        assert_eq!(Ok("BBFFBBFRLL".to_string()), codec.encode(820));
+        assert_eq!(Err(PassError::OutOfRange { seat_id: 1024, max: 1023 }), codec.encode(1024));
+
+        let small = BoardingPassCodec::new(2, 1).unwrap();
+        assert_eq!(Ok(BoardingPass { row: 2, column: 1, board_id: 5 }), small.decode("BFR"));
+        assert_eq!(Ok("FBL".to_string()), small.encode(2));
+        for id in 0..8 {
+            assert_eq!(Ok(id), small.decode(&small.encode(id).unwrap()).map(|p| p.board_id));
+        }
+    }
+
+    #[test]
+    pub fn test_malformed() {
+        assert_eq!(
+            Err(PassError::WrongLength { expected: 10, found: 3 }),
+            "BFR".parse::<BoardingPass>()
+        );
+        assert_eq!(
+            Err(PassError::UnexpectedChar { position: 7, found: 'B', expected: "L or R" }),
+            "BFFFBBFBRR".parse::<BoardingPass>()
+        );
+        assert_eq!(
+            Err(PassError::UnexpectedChar { position: 0, found: 'x', expected: "F or B" }),
+            "xFFFBBFRRR".parse::<BoardingPass>()
+        );
+        assert_eq!(Err(PassError::TooManyBits { bits: 32 }), BoardingPassCodec::new(20, 12));
+    }
+
 }
 
 
@@ -0,0 +1,178 @@
+use super::seatmap::SeatMap;
+use super::BoardingPass;
+
+/// Seats handed to one group. `split` is set when the group could not sit
+/// together in a single row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub group: usize,
+    pub seats: Vec<BoardingPass>,
+    pub passes: Vec<String>,
+    pub split: bool,
+}
+
+/// Seats groups front to back. A group takes the leftmost run of adjacent
+/// free seats in the first row where it fits. If no row has enough, it is
+/// spread over the largest free runs so it ends up in as few pieces as
+/// possible.
+#[derive(Debug, Clone)]
+pub struct Allocator {
+    seat_map: SeatMap,
+    groups: usize,
+}
+
+impl Allocator {
+    pub fn new(seat_map: SeatMap) -> Allocator {
+        Allocator {
+            seat_map,
+            groups: 0,
+        }
+    }
+
+    pub fn seat_map(&self) -> &SeatMap {
+        &self.seat_map
+    }
+
+    /// `None` if there are fewer than `size` free seats left.
+    pub fn allocate(&mut self, size: usize) -> Option<Allocation> {
+        let codec = self.seat_map.codec;
+        if size == 0 || self.seat_map.seat_count() - self.seat_map.taken_count() < size {
+            return None;
+        }
+
+        let mut runs: Vec<(u32, u32, u32)> = (0..codec.rows())
+            .flat_map(|row| {
+                self.seat_map
+                    .free_runs(row)
+                    .into_iter()
+                    .map(move |run| (row, run.start, run.end))
+            })
+            .collect();
+        let together = runs
+            .iter()
+            .find(|(_, start, end)| (end - start) as usize >= size)
+            .map(|&(row, start, _)| (row, start, start + size as u32));
+        let pieces = match together {
+            Some(piece) => vec![piece],
+            None => {
+                runs.sort_by_key(|&(row, start, end)| (std::cmp::Reverse(end - start), row, start));
+                let mut left = size as u32;
+                let mut pieces = Vec::new();
+                for (row, start, end) in runs {
+                    if left == 0 {
+                        break;
+                    }
+                    let take = left.min(end - start);
+                    pieces.push((row, start, start + take));
+                    left -= take;
+                }
+                pieces.sort_unstable();
+                pieces
+            }
+        };
+
+        let seats: Vec<BoardingPass> = pieces
+            .iter()
+            .flat_map(|&(row, start, end)| {
+                (start..end).map(move |column| codec.seat_id(row, column))
+            })
+            .map(|id| codec.seat(id))
+            .collect();
+        for seat in &seats {
+            self.seat_map.issue(seat);
+        }
+        let passes = seats
+            .iter()
+            .map(|seat| codec.encode(seat.board_id).expect("seat is on the plane"))
+            .collect();
+        self.groups += 1;
+        Some(Allocation {
+            group: self.groups - 1,
+            seats,
+            passes,
+            split: together.is_none(),
+        })
+    }
+
+    /// Seats groups until the plane reaches `fill_level` (a share between 0
+    /// and 1). Stops at the first group that would overshoot the level or
+    /// when `group_sizes` runs out.
+    pub fn fill<I: IntoIterator<Item = usize>>(
+        &mut self,
+        fill_level: f64,
+        group_sizes: I,
+    ) -> Vec<Allocation> {
+        let target = (fill_level * self.seat_map.seat_count() as f64).round() as usize;
+        let mut allocations = Vec::new();
+        for size in group_sizes {
+            if self.seat_map.taken_count() + size > target {
+                break;
+            }
+            match self.allocate(size) {
+                Some(allocation) => allocations.push(allocation),
+                None => break,
+            }
+        }
+        allocations
+    }
+}
+
+/// All passes of the allocations, one per line like the puzzle input.
+pub fn boarding_data(allocations: &[Allocation]) -> String {
+    allocations
+        .iter()
+        .flat_map(|a| a.passes.iter())
+        .map(|pass| format!("{}\n", pass))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day05::BoardingPassCodec;
+
+    fn allocator(taken: &[u32]) -> Allocator {
+        let codec = BoardingPassCodec::new(2, 2).unwrap();
+        let passes: Vec<BoardingPass> = taken.iter().map(|&id| codec.seat(id)).collect();
+        Allocator::new(SeatMap::new(codec, &passes).unwrap())
+    }
+
+    #[test]
+    pub fn test_together() {
+        let mut allocator = allocator(&[1, 5]);
+        let first = allocator.allocate(2).unwrap();
+        assert!(!first.split);
+        assert_eq!(vec!["FFRL", "FFRR"], first.passes);
+        let second = allocator.allocate(3).unwrap();
+        assert_eq!(vec!["BFLL", "BFLR", "BFRL"], second.passes);
+        assert_eq!(1, second.group);
+    }
+
+    #[test]
+    pub fn test_split() {
+        let mut allocator = allocator(&[1, 5, 9, 13, 3, 7]);
+        let group = allocator.allocate(5).unwrap();
+        assert!(group.split);
+        assert_eq!(5, group.seats.len());
+        let rows: Vec<u32> = group.seats.iter().map(|s| s.row).collect();
+        assert_eq!(vec![0, 2, 2, 3, 3], rows);
+        assert_eq!(11, allocator.seat_map().taken_count());
+        assert_eq!(None, allocator.allocate(6));
+    }
+
+    #[test]
+    pub fn test_fill() {
+        let mut allocator = allocator(&[]);
+        let allocations = allocator.fill(0.5, vec![3, 2, 2, 4, 1].into_iter().cycle());
+        assert_eq!(3, allocations.len());
+        assert_eq!(7, allocator.seat_map().taken_count());
+        let data = boarding_data(&allocations);
+        assert_eq!(7, data.lines().count());
+        let decoded: Vec<u32> = data
+            .lines()
+            .map(|l| allocator.seat_map().codec.decode(l).unwrap().board_id)
+            .collect();
+        assert_eq!(vec![0, 1, 2, 4, 5, 6, 7], decoded);
+    }
+}
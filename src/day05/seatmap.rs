@@ -0,0 +1,255 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::{BoardingPass, BoardingPassCodec, PassError};
+
+const OCCUPIED: char = '#';
+const FREE: char = '.';
+const DUPLICATE: char = '!';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeatError {
+    NoCandidate,
+    Ambiguous(Vec<u32>),
+}
+
+impl fmt::Display for SeatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeatError::NoCandidate => write!(f, "no free seat between two taken ones"),
+            SeatError::Ambiguous(ids) => write!(f, "several seats could be yours: {:?}", ids),
+        }
+    }
+}
+
+impl std::error::Error for SeatError {}
+
+/// Where the plane is empty: whole rows in front of the first row with a
+/// pass and behind the last one, and every free seat from the first of those
+/// rows to the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingSeats {
+    pub front: Vec<u32>,
+    pub interior: Vec<BoardingPass>,
+    pub back: Vec<u32>,
+}
+
+/// How many passes were issued for every seat of the plane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatMap {
+    pub codec: BoardingPassCodec,
+    passes: Vec<usize>,
+}
+
+impl SeatMap {
+    /// Fails on the first pass for a seat the plane of `codec` does not have.
+    pub fn new(codec: BoardingPassCodec, passes: &[BoardingPass]) -> Result<SeatMap, PassError> {
+        let mut counts = vec![0; (codec.rows() * codec.columns()) as usize];
+        for pass in passes {
+            match counts.get_mut(pass.board_id as usize) {
+                Some(count) => *count += 1,
+                None => {
+                    return Err(PassError::OutOfRange {
+                        seat_id: pass.board_id,
+                        max: codec.seat_id(codec.rows() - 1, codec.columns() - 1),
+                    })
+                }
+            }
+        }
+        Ok(SeatMap {
+            codec,
+            passes: counts,
+        })
+    }
+
+    pub fn passes_for(&self, row: u32, column: u32) -> usize {
+        self.passes[self.codec.seat_id(row, column) as usize]
+    }
+
+    pub fn is_occupied(&self, row: u32, column: u32) -> bool {
+        self.passes_for(row, column) > 0
+    }
+
+    pub fn issue(&mut self, pass: &BoardingPass) {
+        self.passes[pass.board_id as usize] += 1;
+    }
+
+    pub fn seat_count(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn taken_count(&self) -> usize {
+        self.passes.iter().filter(|&&count| count > 0).count()
+    }
+
+    /// Share of seats with at least one pass.
+    pub fn occupancy(&self) -> f64 {
+        self.taken_count() as f64 / self.seat_count() as f64
+    }
+
+    /// Runs of adjacent free columns in `row`, left to right.
+    pub fn free_runs(&self, row: u32) -> Vec<Range<u32>> {
+        let mut runs = Vec::new();
+        let mut start = None;
+        for column in 0..=self.codec.columns() {
+            let free = column < self.codec.columns() && !self.is_occupied(row, column);
+            match (free, start) {
+                (true, None) => start = Some(column),
+                (false, Some(first)) => {
+                    runs.push(first..column);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    /// Seats with more than one pass, along with the number of passes.
+    pub fn duplicates(&self) -> Vec<(BoardingPass, usize)> {
+        self.passes
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 1)
+            .map(|(id, &count)| (self.codec.seat(id as u32), count))
+            .collect()
+    }
+
+    pub fn missing(&self) -> MissingSeats {
+        let rows = self.codec.rows();
+        let row_taken = |row: &u32| (0..self.codec.columns()).any(|c| self.is_occupied(*row, c));
+        let (first, last) = match ((0..rows).find(row_taken), (0..rows).rev().find(row_taken)) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                return MissingSeats {
+                    front: (0..rows).collect(),
+                    interior: Vec::new(),
+                    back: Vec::new(),
+                }
+            }
+        };
+        let seats =
+            self.codec.seat_id(first, 0)..=self.codec.seat_id(last, self.codec.columns() - 1);
+        MissingSeats {
+            front: (0..first).collect(),
+            interior: seats
+                .filter(|&id| self.passes[id as usize] == 0)
+                .map(|id| self.codec.seat(id))
+                .collect(),
+            back: (last + 1..rows).collect(),
+        }
+    }
+
+    /// The only free seat whose neighbours on both sides are taken.
+    pub fn my_seat(&self) -> Result<u32, SeatError> {
+        let candidates: Vec<u32> = self
+            .missing()
+            .interior
+            .iter()
+            .map(|seat| seat.board_id as usize)
+            // the first and last seat of the plane can be interior too
+            .filter(|&id| {
+                id > 0
+                    && self.passes[id - 1] > 0
+                    && self.passes.get(id + 1).is_some_and(|&count| count > 0)
+            })
+            .map(|id| id as u32)
+            .collect();
+        match candidates.len() {
+            0 => Err(SeatError::NoCandidate),
+            1 => Ok(candidates[0]),
+            _ => Err(SeatError::Ambiguous(candidates)),
+        }
+    }
+}
+
+/// One line per row, `#` for taken seats, `.` for free ones and `!` for
+/// seats with more than one pass.
+impl fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = (self.codec.rows() - 1).to_string().len();
+        for row in 0..self.codec.rows() {
+            let seats: String = (0..self.codec.columns())
+                .map(|column| match self.passes_for(row, column) {
+                    0 => FREE,
+                    1 => OCCUPIED,
+                    _ => DUPLICATE,
+                })
+                .collect();
+            writeln!(f, "{:>width$} {}", row, seats, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn map(ids: &[u32]) -> SeatMap {
+        let codec = BoardingPassCodec::new(2, 2).unwrap();
+        let passes: Vec<BoardingPass> = ids
+            .iter()
+            .map(|&id| codec.decode(&codec.encode(id).unwrap()).unwrap())
+            .collect();
+        SeatMap::new(codec, &passes).unwrap()
+    }
+
+    #[test]
+    pub fn test_render() {
+        assert_eq!(
+            "0 ....\n1 ##.#\n2 #!..\n3 ....\n",
+            map(&[4, 5, 7, 8, 9, 9]).to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_missing() {
+        let seat_map = map(&[3, 4, 6, 9, 11]);
+        let ids = |seats: &[BoardingPass]| seats.iter().map(|s| s.board_id).collect::<Vec<_>>();
+        let missing = seat_map.missing();
+        assert!(missing.front.is_empty());
+        assert_eq!(vec![0, 1, 2, 5, 7, 8, 10], ids(&missing.interior));
+        assert_eq!(vec![3], missing.back);
+
+        let missing = map(&[9, 10]).missing();
+        assert_eq!(vec![0, 1], missing.front);
+        assert_eq!(vec![8, 11], ids(&missing.interior));
+        assert_eq!(vec![3], missing.back);
+        assert_eq!(vec![0, 1, 2, 3], map(&[]).missing().front);
+        assert_eq!(Err(SeatError::Ambiguous(vec![5, 10])), seat_map.my_seat());
+        assert!(seat_map.duplicates().is_empty());
+    }
+
+    #[test]
+    pub fn test_free_runs() {
+        let mut seat_map = map(&[1, 6]);
+        assert_eq!(vec![0..1, 2..4], seat_map.free_runs(0));
+        assert_eq!(vec![0..2, 3..4], seat_map.free_runs(1));
+        assert_eq!(vec![0..4], seat_map.free_runs(2));
+        seat_map.issue(&seat_map.codec.seat(0));
+        assert_eq!(vec![2..4], seat_map.free_runs(0));
+        assert_eq!(3.0 / 16.0, seat_map.occupancy());
+    }
+
+    #[test]
+    pub fn test_my_seat() {
+        let seat_map = map(&[5, 6, 8, 8, 9]);
+        assert_eq!(Ok(7), seat_map.my_seat());
+        assert_eq!(vec![(seat_map.codec.seat(8), 2)], seat_map.duplicates());
+        assert_eq!(Err(SeatError::NoCandidate), map(&[5, 6, 7]).my_seat());
+    }
+
+    #[test]
+    pub fn test_off_the_plane() {
+        let passes = [BoardingPassCodec::default().seat(16)];
+        assert_eq!(
+            Err(PassError::OutOfRange {
+                seat_id: 16,
+                max: 15
+            }),
+            SeatMap::new(BoardingPassCodec::new(2, 2).unwrap(), &passes)
+        );
+    }
+}
@@ -1,52 +1,156 @@
-use std::collections::HashMap;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Not};
+use std::str::FromStr;
 
-use itertools::Itertools;
+pub mod analytics;
+pub mod query;
 
+use query::Query;
 
+pub(crate) const QUESTIONS: usize = 26;
 
-#[aoc(day6, part1)]
-pub fn part1(input: &str) -> usize {
-    let mut groups = Vec::new();
-    let mut group = Vec::new();
-    for line in input.lines() {
-        if line.is_empty() {
-            groups.push(group);
-            group = Vec::new();
+/// The questions a person answered "yes" to, one bit per letter `a` to `z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Answers(pub u32);
+
+impl Answers {
+    pub const NONE: Answers = Answers(0);
+    pub const ALL: Answers = Answers((1 << QUESTIONS) - 1);
+
+    pub fn contains(&self, question: char) -> bool {
+        Answers::bit(question).is_some_and(|bit| self.0 & bit != 0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn questions(&self) -> impl Iterator<Item = char> + '_ {
+        (b'a'..=b'z')
+            .map(char::from)
+            .filter(move |&q| self.contains(q))
+    }
+
+    /// The bit of `question`, `None` for anything but `a` to `z`.
+    pub(crate) fn bit(question: char) -> Option<u32> {
+        if question.is_ascii_lowercase() {
+            Some(1 << (question as u32 - 'a' as u32))
         } else {
-            for c in line.chars() {
-                group.push(c);
-            }
+            None
         }
     }
-    if !group.is_empty() {
-        groups.push(group);
+}
+
+impl FromStr for Answers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().try_fold(Answers::NONE, |answers, question| {
+            Answers::bit(question)
+                .map(|bit| Answers(answers.0 | bit))
+                .ok_or_else(|| format!("{:?} is no question", question))
+        })
     }
+}
 
-    groups.iter().map(|group| group.iter().unique().count()).sum()
+impl fmt::Display for Answers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.questions().collect::<String>())
+    }
 }
 
-#[aoc(day6, part2)]
-pub fn part2(input: &str) -> usize {
+impl BitOr for Answers {
+    type Output = Answers;
+
+    fn bitor(self, rhs: Answers) -> Answers {
+        Answers(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Answers {
+    type Output = Answers;
+
+    fn bitand(self, rhs: Answers) -> Answers {
+        Answers(self.0 & rhs.0)
+    }
+}
+
+impl Not for Answers {
+    type Output = Answers;
+
+    fn not(self) -> Answers {
+        Answers(!self.0 & Answers::ALL.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub people: Vec<Answers>,
+}
+
+impl Group {
+    pub fn size(&self) -> usize {
+        self.people.len()
+    }
+
+    pub fn union(&self) -> Answers {
+        self.people.iter().fold(Answers::NONE, |acc, &p| acc | p)
+    }
+
+    pub fn intersection(&self) -> Answers {
+        self.people.iter().fold(Answers::ALL, |acc, &p| acc & p)
+    }
+
+    /// Number of people that answered `question` with "yes".
+    pub fn answered_by(&self, question: char) -> usize {
+        self.people.iter().filter(|p| p.contains(question)).count()
+    }
+}
+
+#[aoc_generator(day6)]
+pub fn generate(input: &str) -> Vec<Group> {
     let mut groups = Vec::new();
-    let mut group = HashMap::new();
-    let mut num_entries = 0;
-    for line in input.lines() {
+    let mut people = Vec::new();
+    for line in input.lines().map(str::trim) {
         if line.is_empty() {
-            groups.push((group, num_entries));
-            group = HashMap::new();
-            num_entries = 0;
-        } else {
-            num_entries += 1;
-            for c in line.chars() {
-                let counter = group.entry(c).or_insert( 0);
-                *counter += 1;
+            if !people.is_empty() {
+                groups.push(Group { people });
+                people = Vec::new();
             }
+        } else {
+            people.push(line.parse().expect("could not parse answers"));
         }
     }
-    if !group.is_empty() {
-        groups.push((group, num_entries));
+    if !people.is_empty() {
+        groups.push(Group { people });
     }
+    groups
+}
+
+#[aoc(day6, part1)]
+pub fn part1(groups: &[Group]) -> usize {
+    Query::Any.run(groups).total
+}
+
+#[aoc(day6, part2)]
+pub fn part2(groups: &[Group]) -> usize {
+    Query::All.run(groups).total
+}
+
+/// The example of the puzzle text.
+#[cfg(test)]
+pub(crate) const EXAMPLE: &str = "abc
+
+a
+b
+c
+
+ab
+ac
 
-    groups.iter().map(|(group, ne)| group.iter().filter(|(_key, count)| count == &ne).count()).sum()
+a
+a
+a
+a
 
-}
\ No newline at end of file
+b";
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{Answers, Group, QUESTIONS};
+
+/// Share of the group's members that answered each question, all zero for a
+/// group without members.
+pub fn profile(group: &Group) -> [f64; QUESTIONS] {
+    let mut profile = [0.0; QUESTIONS];
+    if group.size() == 0 {
+        return profile;
+    }
+    for (idx, q) in ('a'..='z').enumerate() {
+        profile[idx] = group.answered_by(q) as f64 / group.size() as f64;
+    }
+    profile
+}
+
+/// Jaccard index of the questions answered in both groups, two groups
+/// without any answer count as identical.
+pub fn jaccard(a: Answers, b: Answers) -> f64 {
+    let union = (a | b).count();
+    if union == 0 {
+        1.0
+    } else {
+        (a & b).count() as f64 / union as f64
+    }
+}
+
+fn distance(a: &[f64; QUESTIONS], b: &[f64; QUESTIONS]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurveyReport {
+    pub people: usize,
+    /// People that answered each question, `a` first.
+    pub question_people: [usize; QUESTIONS],
+    /// Groups in which anyone answered each question.
+    pub question_groups: [usize; QUESTIONS],
+    /// Number of groups per group size.
+    pub group_sizes: BTreeMap<usize, usize>,
+    /// Jaccard index between the answered questions of every pair of groups.
+    pub similarity: Vec<Vec<f64>>,
+    /// Group index and euclidean distance of its profile to the average
+    /// profile, most unusual first.
+    pub unusual: Vec<(usize, f64)>,
+}
+
+impl SurveyReport {
+    pub fn new(groups: &[Group], top_unusual: usize) -> SurveyReport {
+        let mut question_people = [0; QUESTIONS];
+        let mut question_groups = [0; QUESTIONS];
+        let mut group_sizes = BTreeMap::new();
+        for group in groups {
+            for (idx, q) in ('a'..='z').enumerate() {
+                question_people[idx] += group.answered_by(q);
+                question_groups[idx] += group.union().contains(q) as usize;
+            }
+            *group_sizes.entry(group.size()).or_insert(0) += 1;
+        }
+
+        let unions: Vec<Answers> = groups.iter().map(Group::union).collect();
+        let similarity = unions
+            .iter()
+            .map(|&a| unions.iter().map(|&b| jaccard(a, b)).collect())
+            .collect();
+
+        let profiles: Vec<[f64; QUESTIONS]> = groups.iter().map(profile).collect();
+        let mut average = [0.0; QUESTIONS];
+        for p in &profiles {
+            for (avg, share) in average.iter_mut().zip(p.iter()) {
+                *avg += share / profiles.len() as f64;
+            }
+        }
+        let mut unusual: Vec<(usize, f64)> = profiles
+            .iter()
+            .map(|p| distance(p, &average))
+            .enumerate()
+            .collect();
+        unusual.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        unusual.truncate(top_unusual);
+
+        SurveyReport {
+            people: groups.iter().map(Group::size).sum(),
+            question_people,
+            question_groups,
+            group_sizes,
+            similarity,
+            unusual,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "people: {}, groups: {}",
+            self.people,
+            self.similarity.len()
+        )
+        .unwrap();
+        writeln!(out, "\nquestion  people  groups").unwrap();
+        for (idx, q) in ('a'..='z').enumerate() {
+            writeln!(
+                out,
+                "{:>8}  {:>6}  {:>6}",
+                q, self.question_people[idx], self.question_groups[idx]
+            )
+            .unwrap();
+        }
+        writeln!(out, "\ngroup size  groups").unwrap();
+        for (size, count) in &self.group_sizes {
+            writeln!(out, "{:>10}  {:>6}", size, count).unwrap();
+        }
+        writeln!(out, "\nmost unusual groups\n group  distance").unwrap();
+        for (group, dist) in &self.unusual {
+            writeln!(out, "{:>6}  {:>8.3}", group, dist).unwrap();
+        }
+        writeln!(out, "\nsimilarity").unwrap();
+        write!(out, "{:>5}", "").unwrap();
+        for idx in 0..self.similarity.len() {
+            write!(out, " {:>5}", idx).unwrap();
+        }
+        writeln!(out).unwrap();
+        for (idx, row) in self.similarity.iter().enumerate() {
+            write!(out, "{:>5}", idx).unwrap();
+            for value in row {
+                write!(out, " {:>5.2}", value).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let questions: Vec<String> = ('a'..='z')
+            .enumerate()
+            .map(|(idx, q)| {
+                format!(
+                    "{{\"question\": \"{}\", \"people\": {}, \"groups\": {}}}",
+                    q, self.question_people[idx], self.question_groups[idx]
+                )
+            })
+            .collect();
+        let sizes: Vec<String> = self
+            .group_sizes
+            .iter()
+            .map(|(size, count)| format!("\"{}\": {}", size, count))
+            .collect();
+        let unusual: Vec<String> = self
+            .unusual
+            .iter()
+            .map(|(group, dist)| format!("{{\"group\": {}, \"distance\": {}}}", group, dist))
+            .collect();
+        let similarity: Vec<String> = self
+            .similarity
+            .iter()
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(f64::to_string).collect();
+                format!("[{}]", row.join(", "))
+            })
+            .collect();
+        format!(
+            "{{\n  \"people\": {},\n  \"questions\": [{}],\n  \"group_sizes\": {{{}}},\n  \"unusual\": [{}],\n  \"similarity\": [{}]\n}}\n",
+            self.people,
+            questions.join(", "),
+            sizes.join(", "),
+            unusual.join(", "),
+            similarity.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day06::{generate, EXAMPLE};
+
+    #[test]
+    pub fn test_report() {
+        let report = SurveyReport::new(&generate(EXAMPLE), 2);
+        assert_eq!(11, report.people);
+        assert_eq!([8, 4, 3], report.question_people[..3]);
+        assert_eq!([4, 4, 3], report.question_groups[..3]);
+        assert_eq!(0, report.question_people[25]);
+        let sizes: Vec<(usize, usize)> = report.group_sizes.into_iter().collect();
+        assert_eq!(vec![(1, 2), (2, 1), (3, 1), (4, 1)], sizes);
+        assert_eq!(1.0, report.similarity[0][1]);
+        assert_eq!(1.0 / 3.0, report.similarity[0][3]);
+        assert_eq!(0.0, report.similarity[3][4]);
+        assert_eq!(2, report.unusual.len());
+        assert_eq!(4, report.unusual[0].0);
+    }
+
+    #[test]
+    pub fn test_empty_group() {
+        let groups = vec![Group { people: Vec::new() }, generate("a").remove(0)];
+        assert_eq!([0.0; QUESTIONS], profile(&groups[0]));
+        let report = SurveyReport::new(&groups, 2);
+        assert!(report.unusual.iter().all(|(_, d)| d.is_finite()));
+    }
+
+    #[test]
+    pub fn test_output() {
+        let report = SurveyReport::new(&generate("ab\nb\n\nc"), 1);
+        let text = report.to_text();
+        assert!(text.starts_with("people: 3, groups: 2\n"));
+        assert!(text.contains("\n       b       2       1\n"));
+        assert!(text.contains("\n    0  1.00  0.00\n"));
+        let json = report.to_json();
+        assert!(json.contains("{\"question\": \"b\", \"people\": 2, \"groups\": 1}"));
+        assert!(json.contains("\"group_sizes\": {\"1\": 1, \"2\": 1}"));
+        assert!(json.contains("\"similarity\": [[1, 0], [0, 1]]"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::str::FromStr;
+
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::Pair;
+use pest::Parser;
+
+use super::{Answers, Group};
+
+#[derive(Parser)]
+#[grammar = "surveyquery.pest"]
+pub struct QueryParser;
+
+/// Selects questions of a group by who answered them. Written as text like
+/// `exactly 2`, `at least half`, `person 1 but not person 2` or
+/// `any and not all`, with `not` binding tighter than `and`/`but`, which bind
+/// tighter than `or`. People are counted from 1, `at most` only looks at
+/// questions someone answered and `not` at all 26 questions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    /// Answered by at least one person.
+    Any,
+    /// Answered by everyone.
+    All,
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+    AtLeastHalf,
+    Person(usize),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult {
+    pub per_group: Vec<Answers>,
+    pub total: usize,
+}
+
+impl Query {
+    pub fn eval(&self, group: &Group) -> Answers {
+        let by_count = |accept: &dyn Fn(usize) -> bool| {
+            ('a'..='z')
+                .filter(|&q| accept(group.answered_by(q)))
+                .filter_map(Answers::bit)
+                .fold(Answers::NONE, |acc, bit| Answers(acc.0 | bit))
+        };
+        match self {
+            Query::Any => group.union(),
+            Query::All => group.intersection(),
+            Query::Exactly(k) => by_count(&|n| n == *k),
+            Query::AtLeast(k) => by_count(&|n| n >= *k),
+            Query::AtMost(k) => by_count(&|n| n > 0 && n <= *k),
+            Query::AtLeastHalf => by_count(&|n| 2 * n >= group.size()),
+            Query::Person(p) => p
+                .checked_sub(1)
+                .and_then(|idx| group.people.get(idx))
+                .copied()
+                .unwrap_or(Answers::NONE),
+            Query::Not(q) => !q.eval(group),
+            Query::And(a, b) => a.eval(group) & b.eval(group),
+            Query::Or(a, b) => a.eval(group) | b.eval(group),
+        }
+    }
+
+    pub fn run(&self, groups: &[Group]) -> QueryResult {
+        let per_group: Vec<Answers> = groups.iter().map(|g| self.eval(g)).collect();
+        let total = per_group.iter().map(Answers::count).sum();
+        QueryResult { per_group, total }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Query::Any => write!(f, "any"),
+            Query::All => write!(f, "all"),
+            Query::Exactly(k) => write!(f, "exactly {}", k),
+            Query::AtLeast(k) => write!(f, "at least {}", k),
+            Query::AtMost(k) => write!(f, "at most {}", k),
+            Query::AtLeastHalf => write!(f, "at least half"),
+            Query::Person(p) => write!(f, "person {}", p),
+            Query::Not(q) => write!(f, "not ({})", q),
+            Query::And(a, b) => write!(f, "({}) and ({})", a, b),
+            Query::Or(a, b) => write!(f, "({}) or ({})", a, b),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = Box<Error<Rule>>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let query = QueryParser::parse(Rule::query, s)?
+            .next()
+            .expect("query rule always matches once");
+        build(query.into_inner().next().expect("query has an expression"))
+    }
+}
+
+fn number(pair: Pair<Rule>) -> Result<usize, Box<Error<Rule>>> {
+    pair.as_str().parse().map_err(|e| {
+        Box::new(Error::new_from_span(
+            ErrorVariant::CustomError {
+                message: format!("{} is no count: {}", pair.as_str(), e),
+            },
+            pair.as_span(),
+        ))
+    })
+}
+
+fn build(pair: Pair<Rule>) -> Result<Query, Box<Error<Rule>>> {
+    Ok(match pair.as_rule() {
+        Rule::or_expr | Rule::and_expr => {
+            let combine = if pair.as_rule() == Rule::or_expr {
+                Query::Or
+            } else {
+                Query::And
+            };
+            let operands = pair
+                .into_inner()
+                .map(build)
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut inner = operands.into_iter();
+            let first = inner.next().expect("expression has an operand");
+            inner.fold(first, |acc, q| combine(Box::new(acc), Box::new(q)))
+        }
+        Rule::not_expr => Query::Not(Box::new(build(pair.into_inner().next().unwrap())?)),
+        Rule::any => Query::Any,
+        Rule::all => Query::All,
+        Rule::exactly => Query::Exactly(number(pair.into_inner().next().unwrap())?),
+        Rule::at_most => Query::AtMost(number(pair.into_inner().next().unwrap())?),
+        Rule::at_least => {
+            let bound = pair.into_inner().next().unwrap();
+            match bound.as_rule() {
+                Rule::half => Query::AtLeastHalf,
+                _ => Query::AtLeast(number(bound)?),
+            }
+        }
+        Rule::person => Query::Person(number(pair.into_inner().next().unwrap())?),
+        rule => unreachable!("{:?} is no query", rule),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day06::{generate, part1, part2, EXAMPLE};
+
+    #[test]
+    pub fn test_example() {
+        let groups = generate(EXAMPLE);
+        assert_eq!(11, part1(&groups));
+        assert_eq!(6, part2(&groups));
+        assert_eq!(11, "any".parse::<Query>().unwrap().run(&groups).total);
+        assert_eq!(6, "all".parse::<Query>().unwrap().run(&groups).total);
+    }
+
+    #[test]
+    pub fn test_queries() {
+        let groups = generate("abc\nabd\nae\n");
+        let eval = |q: &str| q.parse::<Query>().unwrap().eval(&groups[0]).to_string();
+        assert_eq!("a", eval("exactly 3"));
+        assert_eq!("b", eval("exactly 2"));
+        assert_eq!("ab", eval("at least half"));
+        assert_eq!("cde", eval("at most 1"));
+        assert_eq!("c", eval("person 1 but not person 2"));
+        assert_eq!("bcde", eval("any and not all"));
+        assert_eq!("ace", eval("person 3 or (person 1 and exactly 1)"));
+        assert_eq!("", eval("person 4"));
+        assert_eq!(
+            Query::Or(
+                Box::new(Query::Any),
+                Box::new(Query::And(
+                    Box::new(Query::All),
+                    Box::new(Query::Not(Box::new(Query::Exactly(1))))
+                ))
+            ),
+            "any or all and not exactly 1".parse().unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_results() {
+        let groups = generate(EXAMPLE);
+        let result = "exactly 1".parse::<Query>().unwrap().run(&groups);
+        let per_group: Vec<String> = result.per_group.iter().map(|a| a.to_string()).collect();
+        assert_eq!(vec!["abc", "abc", "bc", "", "b"], per_group);
+        assert_eq!(9, result.total);
+        assert!("at least".parse::<Query>().is_err());
+        assert!("exactly 1 and".parse::<Query>().is_err());
+        let error = "exactly 99999999999999999999999"
+            .parse::<Query>()
+            .unwrap_err();
+        assert!(error.to_string().contains("1:9"));
+    }
+}
@@ -1,50 +1,100 @@
+use pest::error::{Error, ErrorVariant};
 use pest::Parser;
-use std::collections::{hash_map::DefaultHasher, HashMap};
-use std::hash::{Hash, Hasher};
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod diff;
+pub mod dot;
+pub mod engine;
+pub mod graph;
+pub mod query;
+pub mod route;
+pub mod validate;
+
+use engine::BagEngine;
+use graph::{BagGraph, BagId};
+
 #[derive(Parser)]
 #[grammar = "luggagerules.pest"]
 pub struct LuggageRuleParser;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LuggageRule {
-    pub color: u64,
+    pub color: String,
     pub contents: Option<Vec<Content>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Content {
-    pub color: u64,
+    pub color: String,
     pub amount: u32,
 }
 
-pub fn hash_str(input: &str) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    input.hash(&mut hasher);
-    hasher.finish()
+/// `N colour bag` or `N colour bags`.
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = if self.amount == 1 { "" } else { "s" };
+        write!(f, "{} {} bag{}", self.amount, self.color, plural)
+    }
 }
 
-#[aoc_generator(day7)]
-pub fn generate(input: &str) -> HashMap<u64, LuggageRule> {
-    let parsed = LuggageRuleParser::parse(Rule::file, input).expect("could not parse! input");
+/// The rule in puzzle format, parsing it back gives the same rule.
+impl fmt::Display for LuggageRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bags contain ", self.color)?;
+        match &self.contents {
+            Some(contents) => {
+                let contents: Vec<String> = contents.iter().map(Content::to_string).collect();
+                write!(f, "{}.", contents.join(", "))
+            }
+            None => write!(f, "no other bags."),
+        }
+    }
+}
 
-    let mut all_rules = HashMap::new();
+/// A rule along with the `(line, column)` of its colour and of the colours
+/// of its contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedRule {
+    pub rule: LuggageRule,
+    pub at: (usize, usize),
+    pub contents_at: Vec<(usize, usize)>,
+}
+
+const SHINY_GOLD: &str = "shiny gold";
+
+pub fn parse_rules(input: &str) -> Vec<LuggageRule> {
+    try_parse_rules(input)
+        .expect("could not parse! input")
+        .into_iter()
+        .map(|spanned| spanned.rule)
+        .collect()
+}
+
+pub fn try_parse_rules(input: &str) -> Result<Vec<SpannedRule>, Box<Error<Rule>>> {
+    let parsed = LuggageRuleParser::parse(Rule::file, input)?;
+
+    let mut all_rules = Vec::new();
     for pair in parsed {
         for file_inner_pair in pair.into_inner() {
             let line = match file_inner_pair.as_rule() {
                 Rule::line => {
-                    let mut color = 0;
+                    let mut color = String::new();
+                    let mut at = (0, 0);
                     let mut contents: Vec<Content> = Vec::new();
+                    let mut contents_at = Vec::new();
                     for line_inner_pair in file_inner_pair.into_inner() {
                         match line_inner_pair.as_rule() {
                             Rule::bag => {
-                                color = hash_str(line_inner_pair.as_str());
+                                color = line_inner_pair.as_str().to_string();
+                                at = line_inner_pair.as_span().start_pos().line_col();
                             }
                             Rule::content => {
                                 for content_outer_pair in line_inner_pair.into_inner() {
                                     match content_outer_pair.as_rule() {
                                         Rule::with_content => {
                                             let mut bag_amount = 0;
-                                            let mut content_color = 0;
+                                            let mut content_color = String::new();
                                             for content_inner_pair in
                                                 content_outer_pair.into_inner()
                                             {
@@ -53,11 +103,25 @@ pub fn generate(input: &str) -> HashMap<u64, LuggageRule> {
                                                         bag_amount = content_inner_pair
                                                             .as_str()
                                                             .parse::<u32>()
-                                                            .expect("could not parse amount");
+                                                            .map_err(|e| {
+                                                                Error::new_from_span(
+                                                                    ErrorVariant::CustomError {
+                                                                        message: e.to_string(),
+                                                                    },
+                                                                    content_inner_pair.as_span(),
+                                                                )
+                                                            })?;
                                                     }
                                                     Rule::bag => {
-                                                        content_color =
-                                                            hash_str(content_inner_pair.as_str());
+                                                        content_color = content_inner_pair
+                                                            .as_str()
+                                                            .to_string();
+                                                        contents_at.push(
+                                                            content_inner_pair
+                                                                .as_span()
+                                                                .start_pos()
+                                                                .line_col(),
+                                                        );
                                                     }
                                                     _ => unreachable!(),
                                                 }
@@ -81,102 +145,95 @@ pub fn generate(input: &str) -> HashMap<u64, LuggageRule> {
                     } else {
                         Some(contents)
                     };
-                    Some(LuggageRule {
-                        color,
-                        contents: cont,
+                    Some(SpannedRule {
+                        rule: LuggageRule {
+                            color,
+                            contents: cont,
+                        },
+                        at,
+                        contents_at,
                     })
                 }
                 Rule::EOI => None,
-                _ => panic!(format!("found {:?}", file_inner_pair.as_rule())),
+                _ => panic!("found {:?}", file_inner_pair.as_rule()),
             };
             if let Some(line) = line {
-                all_rules.insert(line.color, line);
+                all_rules.push(line);
             }
         }
     }
 
-    all_rules
+    Ok(all_rules)
 }
 
-lazy_static! {
-    static ref SHINY: u64 = hash_str("shiny gold");
+#[aoc_generator(day7)]
+pub fn generate(input: &str) -> BagGraph {
+    validate::validate(input).unwrap_or_else(|issues| panic!("{}", validate::describe(&issues)))
 }
 
 #[aoc(day7, part1)]
-pub fn part1(all_rules: &HashMap<u64, LuggageRule>) -> usize {
+pub fn part1(graph: &BagGraph) -> usize {
     let mut contains = HashMap::new();
-    for (color, _) in all_rules {
-        does_contain_gold(color, all_rules, &mut contains);
+    for color in graph.bags() {
+        does_contain_gold(color, graph, &mut contains);
     }
     contains.into_iter().filter(|(_, found)| *found).count()
 }
 
+#[aoc(day7, part1, Engine)]
+pub fn part1_engine(graph: &BagGraph) -> usize {
+    BagEngine::new(graph.clone())
+        .holders(SHINY_GOLD)
+        .expect("no shiny gold bag")
+}
+
 pub fn does_contain_gold(
-    color: &u64,
-    all_rules: &HashMap<u64, LuggageRule>,
-    checked: &mut HashMap<u64, bool>,
+    color: BagId,
+    graph: &BagGraph,
+    checked: &mut HashMap<BagId, bool>,
 ) -> bool {
-    if let Some(has_checked) = checked.get(color) {
-        return *has_checked;
+    if let Some(has_checked) = checked.get(&color) {
+        *has_checked
     } else {
-        let does_contain_gold = all_rules
-            .get(color)
-            .map(|rule| {
-                rule.contents
-                    .as_ref()
-                    .map(|o_content| {
-                        o_content.iter().any(|content| {
-                            SHINY.eq(&content.color)
-                                || does_contain_gold(&content.color, all_rules, checked)
-                        })
-                    })
-                    .unwrap_or(false)
-            })
-            .expect(&format!("{} not in map!", color));
-        checked.insert(*color, does_contain_gold);
-        return does_contain_gold;
+        let does_contain_gold = graph.contents(color).iter().any(|&(content, _)| {
+            graph.name(content) == SHINY_GOLD || does_contain_gold(content, graph, checked)
+        });
+        checked.insert(color, does_contain_gold);
+        does_contain_gold
     }
 }
 
 #[aoc(day7, part2)]
-pub fn part2(all_rules: &HashMap<u64, LuggageRule>) -> u32 {
-    let mut num_bags: HashMap<u64, u32> = HashMap::new();
-    calc_num_bags(&hash_str("shiny gold"), all_rules, &mut num_bags)
+pub fn part2(graph: &BagGraph) -> u32 {
+    let mut num_bags: HashMap<BagId, u32> = HashMap::new();
+    let gold = graph.id(SHINY_GOLD).expect("no shiny gold bag");
+    calc_num_bags(gold, graph, &mut num_bags)
+}
+
+#[aoc(day7, part2, Engine)]
+pub fn part2_engine(graph: &BagGraph) -> u64 {
+    BagEngine::new(graph.clone())
+        .total_inside(SHINY_GOLD)
+        .expect("no shiny gold bag")
 }
 
-pub fn calc_num_bags(
-    color: &u64,
-    all_rules: &HashMap<u64, LuggageRule>,
-    num_bags: &mut HashMap<u64, u32>,
-) -> u32 {
-    if let Some(baggies) = num_bags.get(color) {
-        return *baggies;
+pub fn calc_num_bags(color: BagId, graph: &BagGraph, num_bags: &mut HashMap<BagId, u32>) -> u32 {
+    if let Some(baggies) = num_bags.get(&color) {
+        *baggies
     } else {
-        let baggies = all_rules
-            .get(color)
-            .map(|rule| {
-                rule.contents
-                    .as_ref()
-                    .map(|content| {
-                        content
-                            .iter()
-                            .map(|c| c.amount * (calc_num_bags(&c.color, all_rules, num_bags) + 1))
-                            .sum()
-                    })
-                    .unwrap_or(0)
-            })
-            .expect("color not found!");
-        num_bags.insert(*color, baggies);
-        return baggies;
+        let baggies = graph
+            .contents(color)
+            .iter()
+            .map(|&(content, amount)| amount * (calc_num_bags(content, graph, num_bags) + 1))
+            .sum();
+        num_bags.insert(color, baggies);
+        baggies
     }
 }
 
+/// The example of the puzzle text.
 #[cfg(test)]
-mod tests {
-
-    #[test]
-    pub fn test_example() {
-        let example = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+pub(crate) const EXAMPLE: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
 dark orange bags contain 3 bright white bags, 4 muted yellow bags.
 bright white bags contain 1 shiny gold bag.
 muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
@@ -186,10 +243,21 @@ vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
 dotted black bags contain no other bags.
 ";
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    pub fn test_example() {
+        let example = super::EXAMPLE;
         let generated = super::generate(&example);
         let res_1 = super::part1(&generated);
         let res_2 = super::part2(&generated);
         assert_eq!(4, res_1);
         assert_eq!(32, res_2);
+
+        let rules = super::parse_rules(example);
+        let written: String = rules.iter().map(|rule| format!("{}\n", rule)).collect();
+        assert_eq!(example, written);
     }
 }
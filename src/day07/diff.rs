@@ -0,0 +1,225 @@
+use std::fmt;
+
+use super::graph::BagGraph;
+
+/// One content of a rule whose amount changed. `None` means the content is
+/// missing on that side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentChange {
+    pub color: String,
+    pub content: String,
+    pub before: Option<u32>,
+    pub after: Option<u32>,
+}
+
+impl fmt::Display for ContentChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.before, self.after) {
+            (Some(before), Some(after)) => write!(
+                f,
+                "~ {}: {} {} -> {}",
+                self.color, self.content, before, after
+            ),
+            (None, Some(after)) => write!(f, "~ {}: +{} {}", self.color, after, self.content),
+            (Some(before), None) => write!(f, "~ {}: -{} {}", self.color, before, self.content),
+            (None, None) => write!(f, "~ {}: {} unchanged", self.color, self.content),
+        }
+    }
+}
+
+/// Part 1 and part 2 of one colour in both rule sets, `None` where the
+/// colour has no rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Effect {
+    pub color: String,
+    pub holders: (Option<usize>, Option<usize>),
+    pub inside: (Option<u64>, Option<u64>),
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn show<T: fmt::Display>(value: &Option<T>) -> String {
+            value.as_ref().map_or("-".into(), T::to_string)
+        }
+        write!(
+            f,
+            "{} bags: held by {} -> {}, holding {} -> {}",
+            self.color,
+            show(&self.holders.0),
+            show(&self.holders.1),
+            show(&self.inside.0),
+            show(&self.inside.1)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ContentChange>,
+}
+
+fn defined(graph: &BagGraph) -> Vec<&str> {
+    let mut colors: Vec<&str> = graph
+        .bags()
+        .filter(|&id| graph.is_defined(id))
+        .map(|id| graph.name(id))
+        .collect();
+    colors.sort_unstable();
+    colors
+}
+
+/// Colours with a rule in only one of the sets, and contents that differ
+/// between the rules of colours in both, all sorted by colour.
+pub fn diff(before: &BagGraph, after: &BagGraph) -> RuleDiff {
+    let is_defined =
+        |graph: &BagGraph, color: &str| graph.id(color).is_some_and(|id| graph.is_defined(id));
+    let (old, new) = (defined(before), defined(after));
+    let mut changed = Vec::new();
+    for &color in old.iter().filter(|&&c| is_defined(after, c)) {
+        let amounts = |graph: &BagGraph| -> Vec<(String, u32)> {
+            let mut amounts: Vec<(String, u32)> = graph
+                .contents(graph.id(color).unwrap())
+                .iter()
+                .map(|&(content, amount)| (graph.name(content).to_string(), amount))
+                .collect();
+            amounts.sort_unstable();
+            amounts
+        };
+        let (was, is) = (amounts(before), amounts(after));
+        let mut contents: Vec<&String> = was.iter().chain(&is).map(|(c, _)| c).collect();
+        contents.sort_unstable();
+        contents.dedup();
+        for content in contents {
+            let find = |amounts: &[(String, u32)]| {
+                amounts.iter().find(|(c, _)| c == content).map(|&(_, n)| n)
+            };
+            let change = (find(&was), find(&is));
+            if change.0 != change.1 {
+                changed.push(ContentChange {
+                    color: color.into(),
+                    content: content.clone(),
+                    before: change.0,
+                    after: change.1,
+                });
+            }
+        }
+    }
+    RuleDiff {
+        added: new
+            .iter()
+            .filter(|&&c| !is_defined(before, c))
+            .map(|&c| c.into())
+            .collect(),
+        removed: old
+            .iter()
+            .filter(|&&c| !is_defined(after, c))
+            .map(|&c| c.into())
+            .collect(),
+        changed,
+    }
+}
+
+/// How the answers to both parts for `color` change between the sets.
+pub fn effect(before: &BagGraph, after: &BagGraph, color: &str) -> Effect {
+    // a colour only mentioned inside other bags has no rule either
+    let has_rule = |graph: &BagGraph| graph.id(color).is_some_and(|id| graph.is_defined(id));
+    let holders = |graph: &BagGraph| {
+        if has_rule(graph) {
+            graph.ancestors(color).ok().map(|a| a.len())
+        } else {
+            None
+        }
+    };
+    let inside = |graph: &BagGraph| {
+        if has_rule(graph) {
+            graph.total_inside(color).ok()
+        } else {
+            None
+        }
+    };
+    Effect {
+        color: color.into(),
+        holders: (holders(before), holders(after)),
+        inside: (inside(before), inside(after)),
+    }
+}
+
+impl RuleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// `+` for added colours, `-` for removed ones and `~` for changed contents,
+/// one per line.
+impl fmt::Display for RuleDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for color in &self.added {
+            writeln!(f, "+ {}", color)?;
+        }
+        for color in &self.removed {
+            writeln!(f, "- {}", color)?;
+        }
+        for change in &self.changed {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day07::parse_rules;
+
+    #[test]
+    pub fn test_diff() {
+        let before = BagGraph::from_rules(&parse_rules(
+            "light red bags contain 1 shiny gold bag, 2 muted yellow bags.
+muted yellow bags contain 2 shiny gold bags.
+shiny gold bags contain 3 faded blue bags.
+faded blue bags contain no other bags.
+",
+        ));
+        let after = BagGraph::from_rules(&parse_rules(
+            "light red bags contain 4 muted yellow bags, 1 dotted black bag.
+muted yellow bags contain 2 shiny gold bags.
+shiny gold bags contain 3 dotted black bags.
+dotted black bags contain no other bags.
+",
+        ));
+        let diff = diff(&before, &after);
+        assert_eq!(
+            "+ dotted black
+- faded blue
+~ light red: +1 dotted black
+~ light red: muted yellow 2 -> 4
+~ light red: -1 shiny gold
+~ shiny gold: +3 dotted black
+~ shiny gold: -3 faded blue
+",
+            diff.to_string()
+        );
+        assert_eq!(
+            "shiny gold bags: held by 2 -> 2, holding 3 -> 3",
+            effect(&before, &after, "shiny gold").to_string()
+        );
+        assert_eq!(
+            "faded blue bags: held by 3 -> -, holding 0 -> -",
+            effect(&before, &after, "faded blue").to_string()
+        );
+        let referenced = BagGraph::from_rules(&parse_rules(
+            "light red bags contain 1 shiny gold bag, 2 faded blue bags.
+shiny gold bags contain 3 faded blue bags.
+",
+        ));
+        assert_eq!(
+            "faded blue bags: held by 3 -> -, holding 0 -> -",
+            effect(&before, &referenced, "faded blue").to_string()
+        );
+        assert!(super::diff(&after, &after).is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use super::graph::{BagGraph, BagId};
+use super::query::QueryError;
+
+/// Number of colours in the Graphviz `set39` scheme used for depths.
+const DEPTH_COLORS: usize = 9;
+const HIGHLIGHT: &str = "red";
+
+/// Which part of the graph gets drawn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Focus {
+    #[default]
+    All,
+    /// The colour and every bag that holds it.
+    Ancestors(String),
+    /// The colour and every bag inside it.
+    Descendants(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DotOptions {
+    pub focus: Focus,
+    /// Drops bags further than this many steps from the focused colour, or
+    /// from the outermost bags when drawing everything.
+    pub max_depth: Option<usize>,
+    /// Draws one nesting chain from the first colour down to the second in
+    /// red.
+    pub highlight: Option<(String, String)>,
+    pub color_by_depth: bool,
+}
+
+/// Steps from any of `starts`, following `next`.
+fn depths<'a, F>(starts: &[BagId], next: F) -> BTreeMap<BagId, usize>
+where
+    F: Fn(BagId) -> &'a [(BagId, u32)],
+{
+    let mut depth: BTreeMap<BagId, usize> = starts.iter().map(|&id| (id, 0)).collect();
+    let mut todo: VecDeque<BagId> = starts.iter().copied().collect();
+    while let Some(bag) = todo.pop_front() {
+        for &(other, _) in next(bag) {
+            if !depth.contains_key(&other) {
+                depth.insert(other, depth[&bag] + 1);
+                todo.push_back(other);
+            }
+        }
+    }
+    depth
+}
+
+impl BagGraph {
+    /// The graph in Graphviz DOT format, edges labelled with amounts.
+    pub fn to_dot(&self, options: &DotOptions) -> Result<String, QueryError> {
+        let mut depth = match &options.focus {
+            Focus::All => {
+                let outermost: Vec<BagId> = self
+                    .bags()
+                    .filter(|&id| self.containers(id).is_empty())
+                    .collect();
+                depths(&outermost, |id| self.contents(id))
+            }
+            Focus::Ancestors(color) => depths(&[self.lookup(color)?], |id| self.containers(id)),
+            Focus::Descendants(color) => depths(&[self.lookup(color)?], |id| self.contents(id)),
+        };
+        if let Some(max) = options.max_depth {
+            depth.retain(|_, d| *d <= max);
+        }
+
+        let mut highlighted: HashSet<(BagId, BagId)> = HashSet::new();
+        if let Some((from, to)) = &options.highlight {
+            let (from, to) = (self.lookup(from)?, self.lookup(to)?);
+            if let Some(path) = self.path_ids(from, to, 1).first() {
+                highlighted.extend(path.windows(2).map(|w| (w[0], w[1])));
+            }
+        }
+        let on_path: HashSet<BagId> = highlighted.iter().flat_map(|&(a, b)| vec![a, b]).collect();
+
+        let mut dot = String::from("digraph bags {\n");
+        for (&id, &d) in &depth {
+            let mut attrs = Vec::new();
+            if options.color_by_depth {
+                attrs.push(format!(
+                    "style=filled, colorscheme=set39, fillcolor={}",
+                    d % DEPTH_COLORS + 1
+                ));
+            }
+            if on_path.contains(&id) {
+                attrs.push(format!("color={}, penwidth=2", HIGHLIGHT));
+            }
+            write!(dot, "    {:?}", self.name(id)).unwrap();
+            if !attrs.is_empty() {
+                write!(dot, " [{}]", attrs.join(", ")).unwrap();
+            }
+            dot.push_str(";\n");
+        }
+        for &id in depth.keys() {
+            for &(content, amount) in self.contents(id) {
+                if !depth.contains_key(&content) {
+                    continue;
+                }
+                write!(
+                    dot,
+                    "    {:?} -> {:?} [label={}",
+                    self.name(id),
+                    self.name(content),
+                    amount
+                )
+                .unwrap();
+                if highlighted.contains(&(id, content)) {
+                    write!(dot, ", color={}, penwidth=2", HIGHLIGHT).unwrap();
+                }
+                dot.push_str("];\n");
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day07::parse_rules;
+
+    const RULES: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags.
+shiny gold bags contain 3 faded blue bags.
+faded blue bags contain no other bags.
+";
+
+    #[test]
+    pub fn test_dot() {
+        let graph = BagGraph::from_rules(&parse_rules(RULES));
+        let options = DotOptions {
+            focus: Focus::Ancestors("shiny gold".into()),
+            max_depth: Some(1),
+            highlight: Some(("muted yellow".into(), "shiny gold".into())),
+            color_by_depth: true,
+        };
+        assert_eq!(
+            "digraph bags {
+    \"bright white\" [style=filled, colorscheme=set39, fillcolor=2];
+    \"muted yellow\" [style=filled, colorscheme=set39, fillcolor=2, color=red, penwidth=2];
+    \"shiny gold\" [style=filled, colorscheme=set39, fillcolor=1, color=red, penwidth=2];
+    \"bright white\" -> \"shiny gold\" [label=1];
+    \"muted yellow\" -> \"shiny gold\" [label=2, color=red, penwidth=2];
+}
+",
+            graph.to_dot(&options).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_descendants() {
+        let graph = BagGraph::from_rules(&parse_rules(RULES));
+        let options = DotOptions {
+            focus: Focus::Descendants("shiny gold".into()),
+            ..DotOptions::default()
+        };
+        assert_eq!(
+            "digraph bags {
+    \"shiny gold\";
+    \"faded blue\";
+    \"shiny gold\" -> \"faded blue\" [label=3];
+}
+",
+            graph.to_dot(&options).unwrap()
+        );
+        let options = DotOptions {
+            focus: Focus::Descendants("shiny golf".into()),
+            ..DotOptions::default()
+        };
+        assert!(graph.to_dot(&options).is_err());
+    }
+}
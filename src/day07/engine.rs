@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use super::graph::{BagGraph, BagId};
+use super::query::QueryError;
+use super::validate::RuleIssue;
+use super::LuggageRule;
+
+/// A bag graph that keeps its answers between queries. Editing a rule only
+/// forgets what was known about the edited bag and the bags holding it,
+/// everything below is still valid.
+#[derive(Debug, Clone, Default)]
+pub struct BagEngine {
+    graph: BagGraph,
+    /// Whether the first bag eventually holds the second one.
+    holds: HashMap<(BagId, BagId), bool>,
+    /// Number of bags inside a bag.
+    inside: HashMap<BagId, u64>,
+}
+
+impl BagEngine {
+    pub fn new(graph: BagGraph) -> BagEngine {
+        BagEngine {
+            graph,
+            ..BagEngine::default()
+        }
+    }
+
+    pub fn graph(&self) -> &BagGraph {
+        &self.graph
+    }
+
+    /// Number of memoized answers.
+    pub fn cached(&self) -> usize {
+        self.holds.len() + self.inside.len()
+    }
+
+    fn invalidate(&mut self, id: BagId) {
+        let mut stale = self.graph.ancestor_ids(id);
+        stale.insert(id);
+        self.holds.retain(|(outer, _), _| !stale.contains(outer));
+        self.inside.retain(|bag, _| !stale.contains(bag));
+    }
+
+    /// Adds the rule or replaces the one for the same colour. Rules that
+    /// would put a bag inside itself are refused.
+    pub fn set_rule(&mut self, rule: &LuggageRule) -> Result<(), RuleIssue> {
+        let contents = rule.contents.iter().flatten();
+        if contents.clone().any(|content| content.color == rule.color) {
+            return Err(RuleIssue::Cycle(vec![rule.color.clone(); 2]));
+        }
+        // a colour the graph does not know yet is in no bag, so only a known
+        // one can close a longer cycle
+        if let Some(id) = self.graph.id(&rule.color) {
+            for content in contents.filter_map(|content| self.graph.id(&content.color)) {
+                if let Some(path) = self.graph.path_ids(content, id, 1).pop() {
+                    let mut cycle: Vec<String> = vec![rule.color.clone()];
+                    cycle.extend(path.iter().map(|&bag| self.graph.name(bag).to_string()));
+                    return Err(RuleIssue::Cycle(cycle));
+                }
+            }
+            self.invalidate(id);
+        }
+        self.graph.add_rule(rule);
+        Ok(())
+    }
+
+    pub fn remove_rule(&mut self, color: &str) -> Result<(), QueryError> {
+        let id = self.graph.lookup(color)?;
+        self.invalidate(id);
+        self.graph.remove_rule(id);
+        Ok(())
+    }
+
+    fn holds_id(&mut self, outer: BagId, target: BagId) -> bool {
+        if let Some(&holds) = self.holds.get(&(outer, target)) {
+            return holds;
+        }
+        let contents = self.graph.contents(outer).to_vec();
+        let holds = contents
+            .iter()
+            .any(|&(content, _)| content == target || self.holds_id(content, target));
+        self.holds.insert((outer, target), holds);
+        holds
+    }
+
+    /// Whether `outer` eventually holds `target`.
+    pub fn holds(&mut self, outer: &str, target: &str) -> Result<bool, QueryError> {
+        let (outer, target) = (self.graph.lookup(outer)?, self.graph.lookup(target)?);
+        Ok(self.holds_id(outer, target))
+    }
+
+    /// Number of bags eventually holding `target`, the answer to part 1.
+    pub fn holders(&mut self, target: &str) -> Result<usize, QueryError> {
+        let target = self.graph.lookup(target)?;
+        Ok((0..self.graph.len())
+            .filter(|&outer| self.holds_id(outer, target))
+            .count())
+    }
+
+    fn inside_id(&mut self, id: BagId) -> u64 {
+        if let Some(&inside) = self.inside.get(&id) {
+            return inside;
+        }
+        let contents = self.graph.contents(id).to_vec();
+        let inside = contents
+            .iter()
+            .map(|&(content, amount)| amount as u64 * (self.inside_id(content) + 1))
+            .sum();
+        self.inside.insert(id, inside);
+        inside
+    }
+
+    /// Number of bags inside `color`, the answer to part 2.
+    pub fn total_inside(&mut self, color: &str) -> Result<u64, QueryError> {
+        let id = self.graph.lookup(color)?;
+        Ok(self.inside_id(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day07::{parse_rules, EXAMPLE};
+
+    fn rule(text: &str) -> LuggageRule {
+        parse_rules(text).remove(0)
+    }
+
+    #[test]
+    pub fn test_edits() {
+        let mut engine = BagEngine::new(BagGraph::from_rules(&parse_rules(EXAMPLE)));
+        assert_eq!(4, engine.holders("shiny gold").unwrap());
+        assert_eq!(32, engine.total_inside("shiny gold").unwrap());
+
+        engine
+            .set_rule(&rule("vibrant plum bags contain 1 faded blue bag."))
+            .unwrap();
+        // vibrant plum and everything holding it is forgotten, the rest is kept
+        assert!(engine
+            .inside
+            .contains_key(&engine.graph.id("dark olive").unwrap()));
+        assert!(!engine
+            .inside
+            .contains_key(&engine.graph.id("shiny gold").unwrap()));
+        assert_eq!(12, engine.total_inside("shiny gold").unwrap());
+
+        engine.remove_rule("bright white").unwrap();
+        assert_eq!(3, engine.holders("shiny gold").unwrap());
+        assert!(engine.holds("dark orange", "shiny gold").unwrap());
+
+        engine
+            .set_rule(&rule("pale teal bags contain 2 light red bags."))
+            .unwrap();
+        assert_eq!(4, engine.holders("shiny gold").unwrap());
+        assert_eq!(148, engine.total_inside("pale teal").unwrap());
+    }
+
+    #[test]
+    pub fn test_cycle() {
+        let mut engine = BagEngine::new(BagGraph::from_rules(&parse_rules(EXAMPLE)));
+        assert_eq!(
+            Err(RuleIssue::Cycle(vec![
+                "shiny gold".into(),
+                "bright white".into(),
+                "shiny gold".into()
+            ])),
+            engine.set_rule(&rule("shiny gold bags contain 1 bright white bag."))
+        );
+        assert_eq!(32, engine.total_inside("shiny gold").unwrap());
+        assert_eq!(
+            Err(RuleIssue::Cycle(vec!["x y".into(), "x y".into()])),
+            engine.set_rule(&rule("x y bags contain 1 x y bag."))
+        );
+        assert!(engine.graph().id("x y").is_none());
+    }
+}
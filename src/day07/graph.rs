@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use super::{Content, LuggageRule};
+
+/// Index of an interned bag colour.
+pub type BagId = usize;
+
+/// Bag colours with the bags they contain and the bags they are contained
+/// by. Colours are interned on first sight, so colours that only show up as
+/// contents exist as well but are not `defined`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BagGraph {
+    names: Vec<String>,
+    ids: HashMap<String, BagId>,
+    defined: Vec<bool>,
+    contains: Vec<Vec<(BagId, u32)>>,
+    contained_by: Vec<Vec<(BagId, u32)>>,
+}
+
+impl BagGraph {
+    pub fn new() -> BagGraph {
+        BagGraph::default()
+    }
+
+    pub fn from_rules(rules: &[LuggageRule]) -> BagGraph {
+        let mut graph = BagGraph::new();
+        for rule in rules {
+            graph.add_rule(rule);
+        }
+        graph
+    }
+
+    pub fn intern(&mut self, name: &str) -> BagId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.into());
+        self.ids.insert(name.into(), id);
+        self.defined.push(false);
+        self.contains.push(Vec::new());
+        self.contained_by.push(Vec::new());
+        id
+    }
+
+    pub fn id(&self, name: &str) -> Option<BagId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: BagId) -> &str {
+        &self.names[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn bags(&self) -> impl Iterator<Item = BagId> {
+        0..self.len()
+    }
+
+    /// Whether a rule for this colour exists, not just a reference to it.
+    pub fn is_defined(&self, id: BagId) -> bool {
+        self.defined[id]
+    }
+
+    /// Adds the rule, replacing an earlier rule for the same colour.
+    pub fn add_rule(&mut self, rule: &LuggageRule) -> BagId {
+        let id = self.intern(&rule.color);
+        let contents = rule
+            .contents
+            .iter()
+            .flatten()
+            .map(|c| (self.intern(&c.color), c.amount))
+            .collect();
+        self.set_contents(id, contents);
+        self.defined[id] = true;
+        id
+    }
+
+    /// Drops the rule for `id`, the colour stays known.
+    pub fn remove_rule(&mut self, id: BagId) {
+        self.set_contents(id, Vec::new());
+        self.defined[id] = false;
+    }
+
+    pub fn set_contents(&mut self, id: BagId, contents: Vec<(BagId, u32)>) {
+        for (old, _) in std::mem::take(&mut self.contains[id]) {
+            self.contained_by[old].retain(|&(container, _)| container != id);
+        }
+        for &(content, amount) in &contents {
+            self.contained_by[content].push((id, amount));
+        }
+        self.contains[id] = contents;
+    }
+
+    /// Bags directly inside `id` with their amount.
+    pub fn contents(&self, id: BagId) -> &[(BagId, u32)] {
+        &self.contains[id]
+    }
+
+    /// Bags that directly hold `id` with the amount they hold.
+    pub fn containers(&self, id: BagId) -> &[(BagId, u32)] {
+        &self.contained_by[id]
+    }
+
+    pub fn rule(&self, id: BagId) -> LuggageRule {
+        let contents: Vec<Content> = self.contains[id]
+            .iter()
+            .map(|&(content, amount)| Content {
+                color: self.name(content).into(),
+                amount,
+            })
+            .collect();
+        LuggageRule {
+            color: self.name(id).into(),
+            contents: if contents.is_empty() {
+                None
+            } else {
+                Some(contents)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day07::parse_rules;
+
+    #[test]
+    pub fn test_graph() {
+        let rules = parse_rules(
+            "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags.
+",
+        );
+        let mut graph = BagGraph::from_rules(&rules);
+        assert_eq!(4, graph.len());
+        let gold = graph.id("shiny gold").unwrap();
+        let yellow = graph.id("muted yellow").unwrap();
+        assert!(!graph.is_defined(gold));
+        let containers: Vec<&str> = graph
+            .containers(gold)
+            .iter()
+            .map(|&(c, _)| graph.name(c))
+            .collect();
+        assert_eq!(vec!["bright white", "muted yellow"], containers);
+        assert_eq!(rules[0], graph.rule(graph.id("light red").unwrap()));
+
+        graph.set_contents(yellow, Vec::new());
+        assert_eq!(1, graph.containers(gold).len());
+        assert_eq!(None, graph.rule(yellow).contents);
+    }
+}
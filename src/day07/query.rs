@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+
+use super::diff::{diff, effect};
+use super::dot::{DotOptions, Focus};
+use super::graph::{BagGraph, BagId};
+use super::validate::{describe, validate};
+
+const DEFAULT_INPUT: &str = "input/2020/day7.txt";
+const USAGE: &str = "usage: bags [--input <file>] <command>
+       bags diff <old file> <new file> [<colour>]
+commands:
+  ancestors <colour>            every bag that eventually holds <colour>
+  descendants <colour>          every bag inside <colour> and how many of it
+  count <colour>                number of bags inside <colour>
+  paths <from> <to> [<limit>]   ways to nest <to> inside <from>, 10 by default
+  cheapest <outer> <target>     the chain carrying <target> in <outer> with the fewest bags
+  shallowest <target>           the outermost bag with the fewest layers above <target>
+  dot [<option>...]             the rules as a Graphviz graph
+dot options:
+  --ancestors <colour>          only <colour> and the bags holding it
+  --descendants <colour>        only <colour> and the bags inside it
+  --depth <n>                   only bags at most <n> steps away
+  --path <from> <to>            highlight one way to nest <to> inside <from>
+  --color-depth                 fill bags by their distance";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnknownColor {
+        color: String,
+        suggestions: Vec<String>,
+    },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::UnknownColor { color, suggestions } if suggestions.is_empty() => {
+                write!(f, "no rule mentions {} bags", color)
+            }
+            QueryError::UnknownColor { color, suggestions } => write!(
+                f,
+                "no rule mentions {} bags, did you mean {}?",
+                color,
+                suggestions.join(" or ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            curr.push(
+                (prev[j] + (ca != *cb) as usize)
+                    .min(prev[j + 1] + 1)
+                    .min(curr[j] + 1),
+            );
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+impl BagGraph {
+    /// Looks up a colour, suggesting close names if there is none.
+    pub fn lookup(&self, color: &str) -> Result<BagId, QueryError> {
+        self.id(color).ok_or_else(|| {
+            let mut close: Vec<(usize, &str)> = self
+                .bags()
+                .map(|id| (levenshtein(color, self.name(id)), self.name(id)))
+                .filter(|&(distance, _)| distance <= 3)
+                .collect();
+            close.sort_unstable();
+            QueryError::UnknownColor {
+                color: color.into(),
+                suggestions: close.into_iter().take(3).map(|(_, n)| n.into()).collect(),
+            }
+        })
+    }
+
+    pub(crate) fn ancestor_ids(&self, id: BagId) -> HashSet<BagId> {
+        let mut seen = HashSet::new();
+        let mut todo = vec![id];
+        while let Some(bag) = todo.pop() {
+            for &(container, _) in self.containers(bag) {
+                if seen.insert(container) {
+                    todo.push(container);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Every bag that holds `color`, directly or not, sorted by name.
+    pub fn ancestors(&self, color: &str) -> Result<Vec<&str>, QueryError> {
+        let mut names: Vec<&str> = self
+            .ancestor_ids(self.lookup(color)?)
+            .into_iter()
+            .map(|id| self.name(id))
+            .collect();
+        names.sort_unstable();
+        Ok(names)
+    }
+
+    /// Bags reachable from `id`, every bag before the bags it contains.
+    pub(crate) fn topological_from(&self, id: BagId) -> Vec<BagId> {
+        fn visit(graph: &BagGraph, id: BagId, seen: &mut HashSet<BagId>, order: &mut Vec<BagId>) {
+            if seen.insert(id) {
+                for &(content, _) in graph.contents(id) {
+                    visit(graph, content, seen, order);
+                }
+                order.push(id);
+            }
+        }
+        let mut order = Vec::new();
+        visit(self, id, &mut HashSet::new(), &mut order);
+        order.reverse();
+        order
+    }
+
+    /// Every bag inside `color` with how many of it there are in total,
+    /// sorted by name.
+    pub fn descendants(&self, color: &str) -> Result<Vec<(&str, u64)>, QueryError> {
+        let root = self.lookup(color)?;
+        let mut multiplicity: HashMap<BagId, u64> = HashMap::new();
+        multiplicity.insert(root, 1);
+        for bag in self.topological_from(root) {
+            let outer = multiplicity[&bag];
+            for &(content, amount) in self.contents(bag) {
+                *multiplicity.entry(content).or_insert(0) += outer * amount as u64;
+            }
+        }
+        multiplicity.remove(&root);
+        let mut descendants: Vec<(&str, u64)> = multiplicity
+            .into_iter()
+            .map(|(id, count)| (self.name(id), count))
+            .collect();
+        descendants.sort_unstable();
+        Ok(descendants)
+    }
+
+    /// Number of bags inside `color`.
+    pub fn total_inside(&self, color: &str) -> Result<u64, QueryError> {
+        Ok(self.descendants(color)?.iter().map(|(_, n)| n).sum())
+    }
+
+    /// Up to `limit` nesting chains from `from` down to `to`, both included.
+    pub fn paths(&self, from: &str, to: &str, limit: usize) -> Result<Vec<Vec<&str>>, QueryError> {
+        let (from, to) = (self.lookup(from)?, self.lookup(to)?);
+        Ok(self
+            .path_ids(from, to, limit)
+            .into_iter()
+            .map(|p| p.into_iter().map(|id| self.name(id)).collect())
+            .collect())
+    }
+
+    pub(crate) fn path_ids(&self, from: BagId, to: BagId, limit: usize) -> Vec<Vec<BagId>> {
+        let useful = self.ancestor_ids(to);
+        let mut paths = Vec::new();
+        let mut path = vec![from];
+        self.collect_paths(to, &useful, &mut path, &mut paths, limit);
+        paths
+    }
+
+    fn collect_paths(
+        &self,
+        to: BagId,
+        useful: &HashSet<BagId>,
+        path: &mut Vec<BagId>,
+        paths: &mut Vec<Vec<BagId>>,
+        limit: usize,
+    ) {
+        let last = *path.last().unwrap();
+        if last == to && path.len() > 1 {
+            paths.push(path.clone());
+            return;
+        }
+        for &(content, _) in self.contents(last) {
+            if paths.len() >= limit {
+                return;
+            }
+            if (content == to || useful.contains(&content)) && !path.contains(&content) {
+                path.push(content);
+                self.collect_paths(to, useful, path, paths, limit);
+                path.pop();
+            }
+        }
+    }
+}
+
+/// Colours are two words, so `shiny gold` may come as one argument or two.
+fn take_color(args: &mut std::slice::Iter<String>) -> Option<String> {
+    let first = args.next()?;
+    if first.contains(' ') {
+        Some(first.clone())
+    } else {
+        args.next().map(|second| format!("{} {}", first, second))
+    }
+}
+
+fn dot_options(args: &mut std::slice::Iter<String>) -> Result<DotOptions, String> {
+    let mut options = DotOptions::default();
+    while let Some(option) = args.next() {
+        match option.as_str() {
+            "--ancestors" => options.focus = Focus::Ancestors(take_color(args).ok_or(USAGE)?),
+            "--descendants" => options.focus = Focus::Descendants(take_color(args).ok_or(USAGE)?),
+            "--depth" => {
+                let depth = args.next().ok_or(USAGE)?;
+                options.max_depth = Some(
+                    depth
+                        .parse()
+                        .map_err(|_| format!("{} is no depth", depth))?,
+                );
+            }
+            "--path" => {
+                let from = take_color(args).ok_or(USAGE)?;
+                options.highlight = Some((from, take_color(args).ok_or(USAGE)?));
+            }
+            "--color-depth" => options.color_by_depth = true,
+            _ => return Err(USAGE.into()),
+        }
+    }
+    Ok(options)
+}
+
+fn read_graph(path: &str) -> Result<BagGraph, String> {
+    let rules = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    validate(&rules).map_err(|issues| format!("{}:\n{}", path, describe(&issues).trim_end()))
+}
+
+fn diff_cli(args: &[String]) -> Result<String, String> {
+    let (old, new) = match args {
+        [old, new, ..] => (read_graph(old)?, read_graph(new)?),
+        _ => return Err(USAGE.into()),
+    };
+    let mut out = diff(&old, &new).to_string();
+    if let Some(color) = take_color(&mut args[2..].iter()) {
+        out += &format!("{}\n", effect(&old, &new, &color));
+    }
+    Ok(out)
+}
+
+/// Runs the `bags` subcommand and returns what it prints.
+pub fn cli(args: &[String]) -> Result<String, String> {
+    if args.first().map(String::as_str) == Some("diff") {
+        return diff_cli(&args[1..]);
+    }
+    let mut input = DEFAULT_INPUT.to_string();
+    let mut args = args;
+    if args.first().map(String::as_str) == Some("--input") {
+        input = args.get(1).ok_or(USAGE)?.clone();
+        args = &args[2..];
+    }
+    let graph = read_graph(&input)?;
+
+    let (command, mut rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest.iter()),
+        None => return Err(USAGE.into()),
+    };
+    if command == "dot" {
+        return graph
+            .to_dot(&dot_options(&mut rest)?)
+            .map_err(|e| e.to_string());
+    }
+    let color = take_color(&mut rest).ok_or(USAGE)?;
+    let out = match command {
+        "ancestors" => graph
+            .ancestors(&color)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|name| format!("{}\n", name))
+            .collect(),
+        "descendants" => graph
+            .descendants(&color)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|(name, count)| format!("{} {}\n", count, name))
+            .collect(),
+        "count" => format!(
+            "{}\n",
+            graph.total_inside(&color).map_err(|e| e.to_string())?
+        ),
+        "paths" => {
+            let to = take_color(&mut rest).ok_or(USAGE)?;
+            let limit = match rest.next() {
+                Some(limit) => limit
+                    .parse()
+                    .map_err(|_| format!("{} is no limit", limit))?,
+                None => 10,
+            };
+            graph
+                .paths(&color, &to, limit)
+                .map_err(|e| e.to_string())?
+                .iter()
+                .map(|path| format!("{}\n", path.join(" > ")))
+                .collect()
+        }
+        "cheapest" => {
+            let target = take_color(&mut rest).ok_or(USAGE)?;
+            match graph
+                .cheapest_route(&color, &target)
+                .map_err(|e| e.to_string())?
+            {
+                Some(route) => format!("{}\n", route),
+                None => format!("{} bags never hold {} bags\n", color, target),
+            }
+        }
+        "shallowest" => match graph.shallowest_route(&color).map_err(|e| e.to_string())? {
+            Some(route) => format!("{}\n", route),
+            None => format!("no bag holds {} bags\n", color),
+        },
+        _ => return Err(USAGE.into()),
+    };
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day07::{parse_rules, EXAMPLE};
+
+    #[test]
+    pub fn test_queries() {
+        let graph = BagGraph::from_rules(&parse_rules(EXAMPLE));
+        assert_eq!(
+            vec!["bright white", "dark orange", "light red", "muted yellow"],
+            graph.ancestors("shiny gold").unwrap()
+        );
+        assert_eq!(
+            vec![
+                ("dark olive", 1),
+                ("dotted black", 16),
+                ("faded blue", 13),
+                ("vibrant plum", 2)
+            ],
+            graph.descendants("shiny gold").unwrap()
+        );
+        assert_eq!(32, graph.total_inside("shiny gold").unwrap());
+        assert_eq!(0, graph.total_inside("faded blue").unwrap());
+        assert_eq!(
+            vec![
+                vec!["light red", "bright white", "shiny gold", "dark olive"],
+                vec!["light red", "muted yellow", "shiny gold", "dark olive"],
+            ],
+            graph.paths("light red", "dark olive", 10).unwrap()
+        );
+        assert_eq!(1, graph.paths("light red", "faded blue", 1).unwrap().len());
+        assert_eq!(5, graph.paths("light red", "faded blue", 10).unwrap().len());
+    }
+
+    #[test]
+    pub fn test_unknown_color() {
+        let graph = BagGraph::from_rules(&parse_rules(EXAMPLE));
+        let error = graph.ancestors("shiny golde").unwrap_err();
+        assert_eq!(
+            "no rule mentions shiny golde bags, did you mean shiny gold?",
+            error.to_string()
+        );
+        assert_eq!(
+            "no rule mentions plaid magenta bags",
+            graph.total_inside("plaid magenta").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_cli() {
+        let path = std::env::temp_dir().join(format!("bags-{}.txt", std::process::id()));
+        fs::write(&path, EXAMPLE).unwrap();
+        let input = path.to_str().unwrap();
+        let args = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        let run = |command: &str| cli(&args(&format!("--input {} {}", input, command)));
+        assert_eq!(Ok("32\n".to_string()), run("count shiny gold"));
+        assert_eq!(
+            Ok("bright white\ndark orange\nlight red\nmuted yellow\n".to_string()),
+            run("ancestors shiny gold")
+        );
+        assert!(run("count").unwrap_err().starts_with("usage"));
+        fs::remove_file(&path).unwrap();
+        assert!(cli(&args("--input")).unwrap_err().starts_with("usage"));
+        assert!(cli(&args("--input nope count shiny gold"))
+            .unwrap_err()
+            .starts_with("could not read nope"));
+    }
+}
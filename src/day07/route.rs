@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::graph::{BagGraph, BagId};
+use super::query::QueryError;
+
+/// A nesting chain from an outer bag down to a target. `bags` counts every
+/// bag of the chain the rules force into the outer one, target included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub chain: Vec<String>,
+    pub bags: u64,
+}
+
+impl Route {
+    /// Bags between the outer one and the target.
+    pub fn layers(&self) -> usize {
+        self.chain.len() - 2
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} bags)", self.chain.join(" > "), self.bags)
+    }
+}
+
+impl BagGraph {
+    fn amount(&self, outer: BagId, content: BagId) -> u64 {
+        self.contents(outer)
+            .iter()
+            .find(|&&(c, _)| c == content)
+            .map_or(0, |&(_, amount)| amount as u64)
+    }
+
+    fn route(&self, chain: &[BagId]) -> Route {
+        let mut copies = 1;
+        let mut bags = 0;
+        for pair in chain.windows(2) {
+            copies *= self.amount(pair[0], pair[1]);
+            bags += copies;
+        }
+        Route {
+            chain: chain.iter().map(|&id| self.name(id).to_string()).collect(),
+            bags,
+        }
+    }
+
+    /// Fewest bags along a chain below `id` that ends in `target`, and the
+    /// next bag of that chain.
+    fn cheapest_below(
+        &self,
+        id: BagId,
+        target: BagId,
+        memo: &mut HashMap<BagId, Option<(u64, BagId)>>,
+    ) -> Option<(u64, BagId)> {
+        if let Some(&cheapest) = memo.get(&id) {
+            return cheapest;
+        }
+        let cheapest = self
+            .contents(id)
+            .iter()
+            .filter_map(|&(content, amount)| {
+                let below = if content == target {
+                    0
+                } else {
+                    self.cheapest_below(content, target, memo)?.0
+                };
+                Some((amount as u64 * (below + 1), content))
+            })
+            .min_by_key(|&(bags, content)| (bags, self.name(content)));
+        memo.insert(id, cheapest);
+        cheapest
+    }
+
+    /// The chain that carries `target` inside `outer` with the fewest bags,
+    /// `None` if `outer` never holds `target`.
+    pub fn cheapest_route(&self, outer: &str, target: &str) -> Result<Option<Route>, QueryError> {
+        let (outer, target) = (self.lookup(outer)?, self.lookup(target)?);
+        let mut memo = HashMap::new();
+        let mut chain = vec![outer];
+        while *chain.last().unwrap() != target {
+            match self.cheapest_below(*chain.last().unwrap(), target, &mut memo) {
+                Some((_, next)) => chain.push(next),
+                None => return Ok(None),
+            }
+        }
+        if chain.len() < 2 {
+            return Ok(None);
+        }
+        Ok(Some(self.route(&chain)))
+    }
+
+    /// Of all outermost bags holding `target`, the one with the fewest
+    /// layers in between, ties going to the first name.
+    pub fn shallowest_route(&self, target: &str) -> Result<Option<Route>, QueryError> {
+        let target = self.lookup(target)?;
+        // the next bag on a shortest chain down to the target
+        let mut next: HashMap<BagId, BagId> = HashMap::new();
+        let mut level = vec![target];
+        while !level.is_empty() {
+            let outermost = level
+                .iter()
+                .filter(|&&id| id != target && self.containers(id).is_empty())
+                .min_by_key(|&&id| self.name(id));
+            if let Some(&outer) = outermost {
+                let mut chain = vec![outer];
+                while let Some(&bag) = next.get(chain.last().unwrap()) {
+                    chain.push(bag);
+                }
+                return Ok(Some(self.route(&chain)));
+            }
+            let mut above = Vec::new();
+            for &bag in &level {
+                for &(container, _) in self.containers(bag) {
+                    if container != target && !next.contains_key(&container) {
+                        next.insert(container, bag);
+                        above.push(container);
+                    }
+                }
+            }
+            level = above;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day07::{parse_rules, EXAMPLE};
+
+    #[test]
+    pub fn test_cheapest() {
+        let graph = BagGraph::from_rules(&parse_rules(EXAMPLE));
+        let route = graph
+            .cheapest_route("dark orange", "shiny gold")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            "dark orange > bright white > shiny gold (6 bags)",
+            route.to_string()
+        );
+        assert_eq!(1, route.layers());
+        assert_eq!(
+            "shiny gold > dark olive > faded blue (4 bags)",
+            graph
+                .cheapest_route("shiny gold", "faded blue")
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            None,
+            graph.cheapest_route("faded blue", "shiny gold").unwrap()
+        );
+        assert_eq!(
+            None,
+            graph.cheapest_route("shiny gold", "shiny gold").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_shallowest() {
+        let graph = BagGraph::from_rules(&parse_rules(EXAMPLE));
+        assert_eq!(
+            "dark orange > bright white > shiny gold (6 bags)",
+            graph
+                .shallowest_route("shiny gold")
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "dark orange > muted yellow > faded blue (40 bags)",
+            graph
+                .shallowest_route("faded blue")
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(None, graph.shallowest_route("light red").unwrap());
+    }
+}
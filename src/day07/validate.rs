@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use pest::error::Error;
+
+use super::graph::{BagGraph, BagId};
+use super::{try_parse_rules, Rule};
+
+/// Something that makes a rule set unfit for queries. Positions are
+/// `(line, column)`, both 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleIssue {
+    Syntax(Box<Error<Rule>>),
+    Duplicate {
+        color: String,
+        first: (usize, usize),
+        again: (usize, usize),
+    },
+    Undefined {
+        color: String,
+        at: (usize, usize),
+    },
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for RuleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleIssue::Syntax(e) => write!(f, "syntax error\n{}", e),
+            RuleIssue::Duplicate {
+                color,
+                first,
+                again,
+            } => write!(
+                f,
+                "{}:{}: {} bags are defined again, first defined at {}:{}",
+                again.0, again.1, color, first.0, first.1
+            ),
+            RuleIssue::Undefined { color, at } => {
+                write!(f, "{}:{}: no rule says what {} bags contain", at.0, at.1, color)
+            }
+            RuleIssue::Cycle(colors) => write!(f, "cycle: {}", colors.join(" > ")),
+        }
+    }
+}
+
+/// One issue per line.
+pub fn describe(issues: &[RuleIssue]) -> String {
+    issues.iter().map(|issue| format!("{}\n", issue)).collect()
+}
+
+/// Every cycle closed by a back edge of a depth first search, each starting
+/// and ending with the same colour.
+pub fn find_cycles(graph: &BagGraph) -> Vec<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        New,
+        Open,
+        Done,
+    }
+
+    fn visit(
+        id: BagId,
+        graph: &BagGraph,
+        marks: &mut Vec<Mark>,
+        stack: &mut Vec<BagId>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        marks[id] = Mark::Open;
+        stack.push(id);
+        for &(content, _) in graph.contents(id) {
+            match marks[content] {
+                Mark::New => visit(content, graph, marks, stack, cycles),
+                Mark::Open => {
+                    let start = stack.iter().position(|&s| s == content).unwrap();
+                    cycles.push(
+                        stack[start..]
+                            .iter()
+                            .chain(Some(&content))
+                            .map(|&s| graph.name(s).to_string())
+                            .collect(),
+                    );
+                }
+                Mark::Done => {}
+            }
+        }
+        stack.pop();
+        marks[id] = Mark::Done;
+    }
+
+    let mut marks = vec![Mark::New; graph.len()];
+    let mut cycles = Vec::new();
+    for id in graph.bags() {
+        if marks[id] == Mark::New {
+            visit(id, graph, &mut marks, &mut Vec::new(), &mut cycles);
+        }
+    }
+    cycles
+}
+
+/// Parses and checks the rules, returning the graph only if no rule is
+/// malformed, defined twice, refers to an undefined colour or takes part in
+/// a cycle.
+pub fn validate(input: &str) -> Result<BagGraph, Vec<RuleIssue>> {
+    let rules = try_parse_rules(input).map_err(|e| vec![RuleIssue::Syntax(e)])?;
+    let mut issues = Vec::new();
+
+    let mut defined: HashMap<&str, (usize, usize)> = HashMap::new();
+    for spanned in &rules {
+        let color = spanned.rule.color.as_str();
+        match defined.get(color) {
+            Some(&first) => issues.push(RuleIssue::Duplicate {
+                color: color.into(),
+                first,
+                again: spanned.at,
+            }),
+            None => {
+                defined.insert(color, spanned.at);
+            }
+        }
+    }
+    for spanned in &rules {
+        let contents = spanned.rule.contents.iter().flatten();
+        for (content, &at) in contents.zip(&spanned.contents_at) {
+            if !defined.contains_key(content.color.as_str()) {
+                issues.push(RuleIssue::Undefined {
+                    color: content.color.clone(),
+                    at,
+                });
+            }
+        }
+    }
+
+    // cycles are looked for among the first definition of every colour
+    let mut graph = BagGraph::new();
+    for spanned in &rules {
+        if defined[spanned.rule.color.as_str()] == spanned.at {
+            graph.add_rule(&spanned.rule);
+        }
+    }
+    issues.extend(find_cycles(&graph).into_iter().map(RuleIssue::Cycle));
+
+    if issues.is_empty() {
+        Ok(graph)
+    } else {
+        Err(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    pub fn test_valid() {
+        let graph = validate(
+            "bright white bags contain 1 shiny gold bag.
+shiny gold bags contain no other bags.
+",
+        )
+        .unwrap();
+        assert_eq!(2, graph.len());
+    }
+
+    #[test]
+    pub fn test_issues() {
+        let issues = validate(
+            "light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 light red bag.
+light red bags contain no other bags.
+",
+        )
+        .unwrap_err();
+        assert_eq!(
+            "3:1: light red bags are defined again, first defined at 1:1
+1:46: no rule says what muted yellow bags contain
+cycle: light red > bright white > light red
+",
+            describe(&issues)
+        );
+    }
+
+    #[test]
+    pub fn test_syntax() {
+        let issues = validate("light red bags contain 1 bright white bag\n").unwrap_err();
+        match &issues[..] {
+            [RuleIssue::Syntax(e)] => assert!(e.to_string().contains("1:")),
+            _ => panic!("expected a syntax error, got {:?}", issues),
+        }
+        let issues = validate("a b bags contain 99999999999 c d bags.\n").unwrap_err();
+        assert!(issues[0].to_string().contains("1:18"));
+    }
+}
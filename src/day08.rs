@@ -1,9 +1,14 @@
-use std::collections::HashSet;
-
 use parse_display::{Display, FromStr};
 
+pub mod asm;
+pub mod debug;
+pub mod repair;
+pub mod vm;
+
+use vm::{Halt, Vm};
+
 #[derive(Display, FromStr, PartialEq, Debug, Clone, Copy, Eq)]
-#[display("{} {val}")]
+#[display("{} {val:+}")]
 #[allow(non_camel_case_types)]
 pub enum Instruction {
     nop { val: i32 },
@@ -11,6 +16,37 @@ pub enum Instruction {
     jmp { val: i32 },
 }
 
+impl Instruction {
+    /// The instruction with `jmp` and `nop` swapped, `None` for `acc`.
+    pub fn flipped(self) -> Option<Instruction> {
+        match self {
+            Instruction::nop { val } => Some(Instruction::jmp { val }),
+            Instruction::jmp { val } => Some(Instruction::nop { val }),
+            Instruction::acc { .. } => None,
+        }
+    }
+
+    /// Where the instruction sends the instruction pointer when it runs at
+    /// `addr`, the target may lie outside the program.
+    pub(crate) fn target(self, addr: usize) -> i64 {
+        match self {
+            Instruction::jmp { val } => addr as i64 + val as i64,
+            Instruction::nop { .. } | Instruction::acc { .. } => addr as i64 + 1,
+        }
+    }
+
+    /// `target` in a program of `len` instructions, `None` if it leaves the
+    /// program. The end of the program is address `len`.
+    pub(crate) fn target_in(self, addr: usize, len: usize) -> Option<usize> {
+        let target = self.target(addr);
+        if target < 0 || target > len as i64 {
+            None
+        } else {
+            Some(target as usize)
+        }
+    }
+}
+
 #[aoc_generator(day8)]
 pub fn generate(input: &str) -> Vec<Instruction> {
     input
@@ -21,81 +57,61 @@ pub fn generate(input: &str) -> Vec<Instruction> {
 
 #[aoc(day8, part1)]
 pub fn part1(input: &[Instruction]) -> i32 {
-    let mut instruction_ptr = 0;
-    let mut accum = 0;
-    let mut visited = HashSet::new();
-    while !visited.contains(&instruction_ptr) {
-        visited.insert(instruction_ptr);
-
-        use Instruction::*;
-        match input[instruction_ptr] {
-            nop { val: _ } => instruction_ptr += 1,
-            acc { val } => {
-                accum += val;
-                instruction_ptr += 1;
-            }
-            jmp { val } => instruction_ptr = ((instruction_ptr as i32) + val) as usize,
-        }
+    let mut vm = Vm::new(input);
+    match vm.run() {
+        Halt::InfiniteLoop { .. } => vm.registers.acc,
+        halt => panic!("expected an infinite loop, {}", halt),
     }
-    accum
 }
 
 #[aoc(day8, part2)]
 pub fn part2(input: &[Instruction]) -> i32 {
-    let mut curr_replace = None; //0 is acc anyway so no changes
-    let mut already_replaced = HashSet::new();
-    loop {
-        match try_path_replacement(input, &curr_replace) {
-            Ok(accum) => return accum,
-            Err(backtrack) => curr_replace = next_backtrack(input, &backtrack, &already_replaced),
-        }
-        already_replaced.insert(curr_replace.unwrap());
+    match repair::repair(input) {
+        Ok(repair) => repair.acc,
+        Err(e) => panic!("{}", e),
     }
 }
 
-pub fn next_backtrack(
-    input: &[Instruction],
-    backtrack: &[usize],
-    already_replaced: &HashSet<usize>,
-) -> Option<usize> {
-    for ptr in backtrack.iter().rev() {
-        if !already_replaced.contains(ptr) {
-            match input[*ptr] {
-                Instruction::nop { val: _ } | Instruction::jmp { val: _ } => return Some(*ptr),
-                Instruction::acc { val: _ } => {}
-            }
-        }
-    }
-    panic!("no viable alternative found!");
+#[aoc(day8, part2, Rewind)]
+pub fn part2_rewind(input: &[Instruction]) -> i32 {
+    let (_, acc) = repair_by_rewind(input).expect("no viable alternative found!");
+    acc
 }
 
-pub fn try_path_replacement(
-    input: &[Instruction],
-    replaced: &Option<usize>,
-) -> Result<i32, Vec<usize>> {
-    let mut instruction_ptr = 0;
-    let mut accum = 0;
-    let mut backtrack = Vec::new();
-    while instruction_ptr < input.len() && !backtrack.contains(&instruction_ptr) {
-        backtrack.push(instruction_ptr);
-
-        let to_replace = replaced.map_or(false, |r| r == instruction_ptr);
-
-        use Instruction::*;
-        match (input[instruction_ptr], to_replace) {
-            (nop { val: _ }, false) | (jmp { val: _ }, true) => instruction_ptr += 1,
-            (acc { val }, _) => {
-                accum += val;
-                instruction_ptr += 1;
-            }
-            (jmp { val }, false) | (nop { val }, true) => {
-                instruction_ptr = ((instruction_ptr as i32) + val) as usize
+/// Runs the program once, then walks back along its path and tries flipping
+/// every `jmp` and `nop` on the way, each time only re-running from the
+/// flipped instruction. Returns the flipped address and the accumulator of
+/// the terminating program.
+pub fn repair_by_rewind(input: &[Instruction]) -> Option<(usize, i32)> {
+    let mut vm = Vm::new(input).recording();
+    if vm.run() == Halt::Terminated {
+        return None;
+    }
+    loop {
+        let ip = vm.registers.ip;
+        if let Some(flipped) = vm.program()[ip].flipped() {
+            let checkpoint = vm.snapshot();
+            let original = vm.patch(ip, flipped);
+            if vm.run() == Halt::Terminated {
+                return Some((ip, vm.registers.acc));
             }
+            vm.rewind(&checkpoint);
+            vm.patch(ip, original);
+        }
+        if !vm.step_back() {
+            return None;
         }
-    }
-    if instruction_ptr >= input.len() {
-        Ok(accum)
-    } else {
-        Err(backtrack)
     }
 }
+
+/// The example of the puzzle text.
+#[cfg(test)]
+pub(crate) const EXAMPLE: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
@@ -0,0 +1,219 @@
+use std::collections::{BTreeSet, HashMap};
+
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::Pair;
+use pest::{Parser, Span};
+
+use super::Instruction;
+
+#[derive(Parser)]
+#[grammar = "bootcode.pest"]
+pub struct BootCodeParser;
+
+/// Name of the label the disassembler puts after the last instruction.
+const END: &str = "end";
+
+fn custom_error(span: Span, message: String) -> Box<Error<Rule>> {
+    Box::new(Error::new_from_span(
+        ErrorVariant::CustomError { message },
+        span,
+    ))
+}
+
+fn instruction(op: &str, val: i32) -> Instruction {
+    match op {
+        "nop" => Instruction::nop { val },
+        "acc" => Instruction::acc { val },
+        "jmp" => Instruction::jmp { val },
+        _ => unreachable!(),
+    }
+}
+
+/// Turns boot code with `name:` labels, `#` comments and blank lines into a
+/// program. `jmp` and `nop` may name a label instead of an offset, offsets
+/// are relative like in the raw format, which assembles as it is.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, Box<Error<Rule>>> {
+    let program = BootCodeParser::parse(Rule::program, source)?
+        .next()
+        .unwrap();
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut instructions: Vec<(Pair<Rule>, Pair<Rule>)> = Vec::new();
+    for line in program.into_inner().filter(|p| p.as_rule() == Rule::line) {
+        for pair in line.into_inner() {
+            let mut inner = pair.clone().into_inner();
+            match pair.as_rule() {
+                Rule::definition => {
+                    let label = inner.next().unwrap();
+                    if labels.insert(label.as_str(), instructions.len()).is_some() {
+                        return Err(custom_error(
+                            label.as_span(),
+                            format!("{} is defined more than once", label.as_str()),
+                        ));
+                    }
+                }
+                Rule::instruction => {
+                    instructions.push((inner.next().unwrap(), inner.next().unwrap()));
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    instructions
+        .iter()
+        .enumerate()
+        .map(|(addr, (op, operand))| {
+            let val = match operand.as_rule() {
+                Rule::number => operand
+                    .as_str()
+                    .parse()
+                    .map_err(|e| custom_error(operand.as_span(), format!("{}", e)))?,
+                Rule::label if op.as_str() == "acc" => {
+                    return Err(custom_error(
+                        operand.as_span(),
+                        "acc takes a number, not a label".into(),
+                    ))
+                }
+                Rule::label => match labels.get(operand.as_str()) {
+                    Some(&target) => target as i32 - addr as i32,
+                    None => {
+                        return Err(custom_error(
+                            operand.as_span(),
+                            format!("{} is not defined", operand.as_str()),
+                        ))
+                    }
+                },
+                _ => unreachable!(),
+            };
+            Ok(instruction(op.as_str(), val))
+        })
+        .collect()
+}
+
+/// Where the offset of a `jmp` or `nop` at `addr` points, the address a
+/// label would name.
+fn target(instruction: Instruction, addr: usize, len: usize) -> Option<usize> {
+    match instruction {
+        Instruction::jmp { val } | Instruction::nop { val } => {
+            Instruction::jmp { val }.target_in(addr, len)
+        }
+        Instruction::acc { .. } => None,
+    }
+}
+
+/// A listing of the program with a label on every address a `jmp` or `nop`
+/// points to. Offsets leaving the program stay numbers. Assembling the
+/// listing gives back the program.
+pub fn disassemble(program: &[Instruction]) -> String {
+    let len = program.len();
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(addr, &instruction)| target(instruction, addr, len))
+        .collect();
+    let name = |addr: usize| {
+        if addr == len {
+            END.to_string()
+        } else {
+            format!("l{}", addr)
+        }
+    };
+
+    let mut listing = String::new();
+    for addr in 0..=len {
+        if targets.contains(&addr) {
+            listing += &format!("{}:\n", name(addr));
+        }
+        let instruction = match program.get(addr) {
+            Some(&instruction) => instruction,
+            None => break,
+        };
+        let (op, val) = match instruction {
+            Instruction::nop { val } => ("nop", val),
+            Instruction::acc { val } => ("acc", val),
+            Instruction::jmp { val } => ("jmp", val),
+        };
+        let operand = match target(instruction, addr, len) {
+            Some(target) => name(target),
+            None => format!("{:+}", val),
+        };
+        listing += &format!("    {} {}\n", op, operand);
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day08::vm::Vm;
+    use crate::day08::{generate, EXAMPLE};
+
+    /// The example with a jump out of the program added.
+    fn source() -> String {
+        format!("{}\njmp -10", EXAMPLE)
+    }
+
+    #[test]
+    pub fn test_assemble() {
+        let source = "# count to three
+    acc +1
+loop: acc +1   # again
+    jmp skip
+
+skip:
+    nop loop
+    acc -0
+done: end:";
+        assert_eq!(
+            generate("acc +1\nacc +1\njmp +1\nnop -2\nacc +0"),
+            assemble(source).unwrap()
+        );
+        assert_eq!(
+            generate("nop +0\njmp -1"),
+            assemble("nop +0\njmp -1").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_errors() {
+        let error = |source| assemble(source).unwrap_err().to_string();
+        assert!(error("jmp nowhere").contains("nowhere is not defined"));
+        assert!(error("a: nop +0\na: jmp a").contains("a is defined more than once"));
+        assert!(error("a: acc a").contains("acc takes a number"));
+        assert!(error("nop +0\nmov +1").contains("2:1"));
+    }
+
+    #[test]
+    pub fn test_disassemble() {
+        let program = generate(&source());
+        assert_eq!(
+            "l0:
+    nop l0
+l1:
+    acc +1
+    jmp l6
+l3:
+    acc +3
+    jmp l1
+    acc -99
+l6:
+    acc +1
+    jmp l3
+    acc +6
+    jmp -10
+",
+            disassemble(&program)
+        );
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let program = generate(&source());
+        assert_eq!(program, assemble(&source()).unwrap());
+        let listing = disassemble(&program);
+        let reassembled = assemble(&listing).unwrap();
+        assert_eq!(program, reassembled);
+        assert_eq!(Vm::new(&program).run(), Vm::new(&reassembled).run());
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use super::vm::{Snapshot, Vm};
+use super::{generate, Instruction};
+
+const DEFAULT_INPUT: &str = "input/2020/day8.txt";
+const USAGE: &str = "usage: debug [--input <file>] [--script <file>]";
+const HELP: &str = "commands:
+  break <addr>                  stop when <addr> is about to run
+  step [<n>]                    run <n> instructions, 1 by default
+  back [<n>]                    undo <n> instructions, 1 by default
+  checkpoint                    remember the current state
+  rewind [<checkpoint>]         go back to a checkpoint or to the start
+  continue                      run until a breakpoint, a watch or a halt
+  print acc|ip                  show a register
+  watch acc <op> <value>        stop once acc <op> <value> becomes true,
+                                <op> is one of == != < <= > >=
+  patch <addr> <instruction>    replace an instruction, like `patch 3 nop +0`
+  list [<radius>]               instructions around the instruction pointer
+  quit                          leave the debugger
+";
+/// Instructions shown on either side of the instruction pointer by `list`.
+const LIST_RADIUS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Ip,
+    Acc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A condition on the accumulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    pub comparison: Comparison,
+    pub value: i32,
+}
+
+impl Watch {
+    pub fn holds(&self, acc: i32) -> bool {
+        match self.comparison {
+            Comparison::Eq => acc == self.value,
+            Comparison::Ne => acc != self.value,
+            Comparison::Lt => acc < self.value,
+            Comparison::Le => acc <= self.value,
+            Comparison::Gt => acc > self.value,
+            Comparison::Ge => acc >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.comparison {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        };
+        write!(f, "acc {} {}", op, self.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Break(usize),
+    Step(usize),
+    Back(usize),
+    Checkpoint,
+    Rewind(Option<usize>),
+    Continue,
+    Print(Register),
+    Watch(Watch),
+    Patch(usize, Instruction),
+    List(usize),
+    Help,
+}
+
+fn number<T: FromStr>(word: Option<&str>, what: &str) -> Result<T, String> {
+    let word = word.ok_or_else(|| format!("missing {}", what))?;
+    word.parse().map_err(|_| format!("{} is no {}", word, what))
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = match words.next() {
+            Some("break") | Some("b") => Command::Break(number(words.next(), "address")?),
+            Some("step") | Some("s") => match words.next() {
+                Some(n) => Command::Step(number(Some(n), "count")?),
+                None => Command::Step(1),
+            },
+            Some("back") => match words.next() {
+                Some(n) => Command::Back(number(Some(n), "count")?),
+                None => Command::Back(1),
+            },
+            Some("checkpoint") => Command::Checkpoint,
+            Some("rewind") => match words.next() {
+                Some(id) => Command::Rewind(Some(number(Some(id), "checkpoint")?)),
+                None => Command::Rewind(None),
+            },
+            Some("continue") | Some("c") => Command::Continue,
+            Some("print") | Some("p") => match words.next() {
+                Some("acc") => Command::Print(Register::Acc),
+                Some("ip") => Command::Print(Register::Ip),
+                _ => return Err("can only print acc or ip".into()),
+            },
+            Some("watch") => {
+                if words.next() != Some("acc") {
+                    return Err("can only watch acc".into());
+                }
+                let comparison = match words.next() {
+                    Some("==") => Comparison::Eq,
+                    Some("!=") => Comparison::Ne,
+                    Some("<") => Comparison::Lt,
+                    Some("<=") => Comparison::Le,
+                    Some(">") => Comparison::Gt,
+                    Some(">=") => Comparison::Ge,
+                    _ => return Err("expected one of == != < <= > >=".into()),
+                };
+                Command::Watch(Watch {
+                    comparison,
+                    value: number(words.next(), "value")?,
+                })
+            }
+            Some("patch") => {
+                let addr = number(words.next(), "address")?;
+                let instruction: Vec<&str> = words.by_ref().collect();
+                let instruction = instruction.join(" ");
+                Command::Patch(
+                    addr,
+                    instruction
+                        .parse()
+                        .map_err(|_| format!("{:?} is no instruction", instruction))?,
+                )
+            }
+            Some("list") | Some("l") => match words.next() {
+                Some(radius) => Command::List(number(Some(radius), "radius")?),
+                None => Command::List(LIST_RADIUS),
+            },
+            Some("help") => Command::Help,
+            Some(other) => return Err(format!("unknown command {}, try help", other)),
+            None => return Err("empty command".into()),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected {}", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+/// A VM with breakpoints and watches, driven by text commands.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    pub vm: Vm,
+    breakpoints: BTreeSet<usize>,
+    watches: Vec<Watch>,
+    /// Checkpoint 0 is the start, checkpoints that were undone are gone.
+    checkpoints: Vec<Option<Snapshot>>,
+}
+
+impl Debugger {
+    pub fn new(vm: Vm) -> Debugger {
+        let vm = vm.recording();
+        let start = vm.snapshot();
+        Debugger {
+            vm,
+            breakpoints: BTreeSet::new(),
+            watches: Vec::new(),
+            checkpoints: vec![Some(start)],
+        }
+    }
+
+    /// Forgets checkpoints past the current step, going forward again might
+    /// take another path.
+    fn drop_undone_checkpoints(&mut self) {
+        let steps = self.vm.steps();
+        for checkpoint in &mut self.checkpoints {
+            if checkpoint.is_some_and(|c| c.steps > steps) {
+                *checkpoint = None;
+            }
+        }
+    }
+
+    fn check_addr(&self, addr: usize) -> Result<(), String> {
+        if addr < self.vm.program().len() {
+            Ok(())
+        } else {
+            Err(format!(
+                "no instruction at {}, the program has {}",
+                addr,
+                self.vm.program().len()
+            ))
+        }
+    }
+
+    /// `addr: instruction`, or the halt reason if the machine cannot go on.
+    fn location(&self) -> String {
+        let acc = self.vm.registers.acc;
+        match self.vm.halted() {
+            Some(halt) => format!("halted: {} (acc {})\n", halt, acc),
+            None => {
+                let ip = self.vm.registers.ip;
+                format!("{}: {} (acc {})\n", ip, self.vm.program()[ip], acc)
+            }
+        }
+    }
+
+    fn listing(&self, radius: usize) -> String {
+        let ip = self.vm.registers.ip;
+        let len = self.vm.program().len();
+        let width = len.to_string().len();
+        (ip.saturating_sub(radius)..ip.saturating_add(radius).saturating_add(1).min(len))
+            .map(|addr| {
+                format!(
+                    "{}{}{:>width$}  {}\n",
+                    if addr == ip { "=>" } else { "  " },
+                    if self.breakpoints.contains(&addr) {
+                        "*"
+                    } else {
+                        " "
+                    },
+                    addr,
+                    self.vm.program()[addr],
+                    width = width
+                )
+            })
+            .collect()
+    }
+
+    fn resume(&mut self) -> String {
+        loop {
+            let before = self.vm.registers.acc;
+            if self.vm.step().is_some() {
+                return self.location();
+            }
+            let acc = self.vm.registers.acc;
+            if let Some(watch) = self
+                .watches
+                .iter()
+                .find(|w| w.holds(acc) && !w.holds(before))
+            {
+                return format!("watch {} hit\n{}", watch, self.location());
+            }
+            if self.breakpoints.contains(&self.vm.registers.ip) {
+                return format!("breakpoint\n{}", self.location());
+            }
+        }
+    }
+
+    /// Runs one command and returns what it prints.
+    pub fn execute(&mut self, command: Command) -> Result<String, String> {
+        Ok(match command {
+            Command::Break(addr) => {
+                self.check_addr(addr)?;
+                self.breakpoints.insert(addr);
+                format!("breakpoint at {}\n", addr)
+            }
+            Command::Step(n) => {
+                for _ in 0..n {
+                    if self.vm.step().is_some() {
+                        break;
+                    }
+                }
+                self.location()
+            }
+            Command::Back(n) => {
+                for _ in 0..n {
+                    if !self.vm.step_back() {
+                        break;
+                    }
+                }
+                self.drop_undone_checkpoints();
+                self.location()
+            }
+            Command::Checkpoint => {
+                self.checkpoints.push(Some(self.vm.snapshot()));
+                format!("checkpoint {}\n", self.checkpoints.len() - 1)
+            }
+            Command::Rewind(id) => {
+                let id = id.unwrap_or(0);
+                let checkpoint = self
+                    .checkpoints
+                    .get(id)
+                    .copied()
+                    .flatten()
+                    .ok_or_else(|| format!("no checkpoint {}", id))?;
+                self.vm.rewind(&checkpoint);
+                self.drop_undone_checkpoints();
+                self.location()
+            }
+            Command::Continue => self.resume(),
+            Command::Print(Register::Acc) => format!("acc = {}\n", self.vm.registers.acc),
+            Command::Print(Register::Ip) => format!("ip = {}\n", self.vm.registers.ip),
+            Command::Watch(watch) => {
+                self.watches.push(watch);
+                format!("watching {}\n", watch)
+            }
+            Command::Patch(addr, instruction) => {
+                self.check_addr(addr)?;
+                let old = self.vm.patch(addr, instruction);
+                format!("{}: {} -> {}\n", addr, old, instruction)
+            }
+            Command::List(radius) => self.listing(radius),
+            Command::Help => HELP.into(),
+        })
+    }
+
+    /// Runs a line of text, errors are part of the output.
+    pub fn execute_line(&mut self, line: &str) -> String {
+        match line.parse().and_then(|command| self.execute(command)) {
+            Ok(out) => out,
+            Err(e) => format!("error: {}\n", e),
+        }
+    }
+
+    /// Runs every command of a script, skipping blank lines and `#`
+    /// comments, and returns a transcript with each command echoed after
+    /// `> `.
+    pub fn run_script(&mut self, script: &str) -> String {
+        let mut transcript = String::new();
+        for line in script.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "quit" {
+                break;
+            }
+            transcript += &format!("> {}\n", line);
+            transcript += &self.execute_line(line);
+        }
+        transcript
+    }
+}
+
+/// Runs the `debug` subcommand, reading commands from standard input unless
+/// a script is given.
+pub fn cli(args: &[String]) -> Result<String, String> {
+    let mut input = DEFAULT_INPUT.to_string();
+    let mut script = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or(USAGE)?.clone(),
+            "--script" => script = Some(args.next().ok_or(USAGE)?.clone()),
+            _ => return Err(USAGE.into()),
+        }
+    }
+    let read = |path: &str| {
+        fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))
+    };
+    let program = read(&input)?;
+    let mut debugger = Debugger::new(Vm::new(&generate(&program)));
+    if let Some(script) = script {
+        return Ok(debugger.run_script(&read(&script)?));
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    print!("{}", debugger.location());
+    loop {
+        print!("(vm) ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => break,
+        };
+        match line.trim() {
+            "" => continue,
+            "quit" | "q" => break,
+            line => print!("{}", debugger.execute_line(line)),
+        }
+    }
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day08::EXAMPLE;
+
+    fn debugger() -> Debugger {
+        Debugger::new(Vm::new(&generate(EXAMPLE)))
+    }
+
+    #[test]
+    pub fn test_script() {
+        let script = "# stop in the loop
+break 4
+watch acc >= 2
+continue
+continue
+print acc
+
+patch 4 nop -3
+step 2
+list 2
+continue
+";
+        assert_eq!(
+            "> break 4
+breakpoint at 4
+> watch acc >= 2
+watching acc >= 2
+> continue
+watch acc >= 2 hit
+7: jmp -4 (acc 2)
+> continue
+breakpoint
+4: jmp -3 (acc 5)
+> print acc
+acc = 5
+> patch 4 nop -3
+4: jmp -3 -> nop -3
+> step 2
+halted: infinite loop at 6 (acc -94)
+> list 2
+  *4  nop -3
+   5  acc -99
+=> 6  acc +1
+   7  jmp -4
+   8  acc +6
+> continue
+halted: infinite loop at 6 (acc -94)
+",
+            debugger().run_script(script)
+        );
+    }
+
+    #[test]
+    pub fn test_rewind() {
+        let script = "step 3
+checkpoint
+continue
+back 2
+rewind 1
+patch 7 nop -4
+continue
+rewind
+print acc
+rewind 1
+";
+        assert_eq!(
+            "> step 3
+6: acc +1 (acc 1)
+> checkpoint
+checkpoint 1
+> continue
+halted: infinite loop at 1 (acc 5)
+> back 2
+3: acc +3 (acc 2)
+> rewind 1
+6: acc +1 (acc 1)
+> patch 7 nop -4
+7: jmp -4 -> nop -4
+> continue
+halted: terminated (acc 8)
+> rewind
+0: nop +0 (acc 0)
+> print acc
+acc = 0
+> rewind 1
+error: no checkpoint 1
+",
+            debugger().run_script(script)
+        );
+    }
+
+    #[test]
+    pub fn test_errors() {
+        let mut debugger = debugger();
+        assert_eq!(
+            "error: no instruction at 9, the program has 9\n",
+            debugger.execute_line("break 9")
+        );
+        assert_eq!(
+            "error: \"nop\" is no instruction\n",
+            debugger.execute_line("patch 1 nop")
+        );
+        assert_eq!(
+            "error: no checkpoint 1\n",
+            debugger.execute_line("rewind 1")
+        );
+        assert_eq!(
+            "error: unknown command jump, try help\n",
+            debugger.execute_line("jump 3")
+        );
+        let listing = debugger.execute_line(&format!("list {}", usize::MAX));
+        assert!(listing.starts_with("=> 0  nop +0\n"));
+        assert_eq!(9, listing.lines().count());
+        assert_eq!(
+            Ok(Command::Watch(Watch {
+                comparison: Comparison::Ne,
+                value: -2
+            })),
+            "watch acc != -2".parse()
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::vm::{Halt, Vm};
+use super::Instruction;
+
+/// Where the instruction at `addr` leads, `None` for jumps out of the
+/// program. The end of the program is address `program.len()`.
+pub fn successor(program: &[Instruction], addr: usize) -> Option<usize> {
+    program[addr].target_in(addr, program.len())
+}
+
+/// For every address and the end, whether running from there terminates.
+/// Walks the edges backwards from the end, so every address is looked at
+/// once.
+pub fn terminating(program: &[Instruction]) -> Vec<bool> {
+    let mut predecessors = vec![Vec::new(); program.len() + 1];
+    for addr in 0..program.len() {
+        if let Some(next) = successor(program, addr) {
+            predecessors[next].push(addr);
+        }
+    }
+    let mut terminating = vec![false; program.len() + 1];
+    terminating[program.len()] = true;
+    let mut todo = vec![program.len()];
+    while let Some(addr) = todo.pop() {
+        for &prev in &predecessors[addr] {
+            if !terminating[prev] {
+                terminating[prev] = true;
+                todo.push(prev);
+            }
+        }
+    }
+    terminating
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repair {
+    pub addr: usize,
+    pub from: Instruction,
+    pub to: Instruction,
+    pub acc: i32,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}, acc {}",
+            self.addr, self.from, self.to, self.acc
+        )
+    }
+}
+
+/// Why no single flip works: every `jmp` or `nop` the program runs before
+/// it halts, together with where it would lead when flipped, and none of
+/// those places is among the `terminating` addresses. Flips that do lead
+/// there but halt on the way, like on an overflow, are in `stopped`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub halt: Halt,
+    pub flips: Vec<(usize, Option<usize>)>,
+    pub stopped: Vec<(usize, Halt)>,
+    pub terminating: Vec<usize>,
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "the program halts with {}; only these {} addresses reach the end: {:?}",
+            self.halt,
+            self.terminating.len(),
+            self.terminating
+        )?;
+        for (addr, target) in &self.flips {
+            match target {
+                Some(target) => writeln!(f, "flipping {} leads to {}", addr, target)?,
+                None => writeln!(f, "flipping {} jumps out of the program", addr)?,
+            }
+        }
+        for (addr, halt) in &self.stopped {
+            writeln!(f, "flipping {} halts with {}", addr, halt)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairError {
+    NotBroken { acc: i32 },
+    NoSingleFlip(Proof),
+}
+
+impl fmt::Display for RepairError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepairError::NotBroken { acc } => {
+                write!(f, "the program already terminates with acc {}", acc)
+            }
+            RepairError::NoSingleFlip(proof) => write!(f, "no single flip works, {}", proof),
+        }
+    }
+}
+
+impl std::error::Error for RepairError {}
+
+/// Finds the one `jmp`/`nop` on the executed path whose flip leads into an
+/// address that terminates, in time linear in the program length.
+pub fn repair(program: &[Instruction]) -> Result<Repair, RepairError> {
+    let mut vm = Vm::new(program).recording();
+    let halt = vm.run();
+    if halt == Halt::Terminated {
+        return Err(RepairError::NotBroken {
+            acc: vm.registers.acc,
+        });
+    }
+    let terminating = terminating(program);
+    let mut path: Vec<usize> = vm.trace().collect();
+    // a jump out of the program did not run but can be flipped as well
+    if let Halt::OutOfBounds { .. } = halt {
+        path.push(vm.registers.ip);
+    }
+
+    let mut flips = Vec::new();
+    let mut stopped = Vec::new();
+    for addr in path {
+        let flipped = match program[addr].flipped() {
+            Some(flipped) => flipped,
+            None => continue,
+        };
+        let target = flipped.target_in(addr, program.len());
+        if target.is_some_and(|t| terminating[t]) {
+            let mut vm = Vm::new(program);
+            vm.patch(addr, flipped);
+            // the end is reachable, but the accumulator may overflow on the way
+            match vm.run() {
+                Halt::Terminated => {
+                    return Ok(Repair {
+                        addr,
+                        from: program[addr],
+                        to: flipped,
+                        acc: vm.registers.acc,
+                    })
+                }
+                halt => stopped.push((addr, halt)),
+            }
+            continue;
+        }
+        flips.push((addr, target));
+    }
+    Err(RepairError::NoSingleFlip(Proof {
+        halt,
+        flips,
+        stopped,
+        terminating: (0..program.len()).filter(|&a| terminating[a]).collect(),
+    }))
+}
+
+/// For every address and the end, the fewest flips that could make running
+/// from there terminate, `None` if no number of flips would. Ignores that a
+/// run may not come back to an address, so this is a lower bound.
+pub fn flip_distance(program: &[Instruction]) -> Vec<Option<usize>> {
+    let len = program.len();
+    let mut predecessors = vec![Vec::new(); len + 1];
+    for (addr, &instruction) in program.iter().enumerate() {
+        if let Some(next) = instruction.target_in(addr, len) {
+            predecessors[next].push((addr, 0));
+        }
+        if let Some(next) = instruction.flipped().and_then(|f| f.target_in(addr, len)) {
+            predecessors[next].push((addr, 1));
+        }
+    }
+    let mut distance = vec![None; len + 1];
+    distance[len] = Some(0);
+    let mut todo = VecDeque::from(vec![len]);
+    while let Some(addr) = todo.pop_front() {
+        let here = distance[addr].unwrap();
+        for &(prev, cost) in &predecessors[addr] {
+            if distance[prev].is_none_or(|d| d > here + cost) {
+                distance[prev] = Some(here + cost);
+                if cost == 0 {
+                    todo.push_front(prev);
+                } else {
+                    todo.push_back(prev);
+                }
+            }
+        }
+    }
+    distance
+}
+
+/// A set of flipped addresses that makes the program terminate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub addrs: Vec<usize>,
+    pub acc: i32,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let addrs: Vec<String> = self.addrs.iter().map(usize::to_string).collect();
+        write!(f, "flip {}: acc {}", addrs.join(", "), self.acc)
+    }
+}
+
+/// Runs `vm` from where it stands with at most `left` more flips, trying
+/// every instruction of the run from the last one backwards that is not
+/// flipped yet, and leaves the machine as it found it.
+fn search(vm: &mut Vm, flips: &mut Vec<usize>, left: usize, fixes: &mut Vec<Fix>) {
+    let start = vm.snapshot();
+    if vm.run() == Halt::Terminated {
+        let mut addrs = flips.clone();
+        addrs.sort_unstable();
+        fixes.push(Fix {
+            addrs,
+            acc: vm.registers.acc,
+        });
+    } else if left > 0 {
+        let len = vm.program().len();
+        let distance = flip_distance(vm.program());
+        loop {
+            let ip = vm.registers.ip;
+            // the run starts on the last flip, flipping it back undoes it
+            if ip < len && !vm.visited(ip) && !flips.contains(&ip) {
+                if let Some(flipped) = vm.program()[ip].flipped() {
+                    let target = flipped.target_in(ip, len);
+                    if target.is_some_and(|t| distance[t].is_some_and(|d| d < left)) {
+                        let original = vm.patch(ip, flipped);
+                        flips.push(ip);
+                        search(vm, flips, left - 1, fixes);
+                        flips.pop();
+                        vm.patch(ip, original);
+                    }
+                }
+            }
+            if vm.steps() == start.steps {
+                break;
+            }
+            vm.step_back();
+        }
+    }
+    vm.rewind(&start);
+}
+
+/// Every smallest set of at most `max_flips` flips that makes the program
+/// terminate, sorted. A fix only ever flips instructions that run, so after
+/// each flip only the rest of the run is searched, and flips whose target
+/// cannot reach the end with the flips left are skipped.
+pub fn repair_all(program: &[Instruction], max_flips: usize) -> Vec<Fix> {
+    let mut vm = Vm::new(program).recording();
+    for flips in 0..=max_flips {
+        let mut fixes = Vec::new();
+        search(&mut vm, &mut Vec::new(), flips, &mut fixes);
+        if !fixes.is_empty() {
+            fixes.sort_unstable_by(|a, b| a.addrs.cmp(&b.addrs));
+            fixes.dedup();
+            return fixes;
+        }
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day08::{generate, EXAMPLE};
+
+    #[test]
+    pub fn test_repair() {
+        let program = generate(EXAMPLE);
+        assert_eq!(
+            vec![false, false, false, false, false, false, false, false, true, true],
+            terminating(&program)
+        );
+        assert_eq!(
+            Ok(Repair {
+                addr: 7,
+                from: Instruction::jmp { val: -4 },
+                to: Instruction::nop { val: -4 },
+                acc: 8
+            }),
+            repair(&program)
+        );
+    }
+
+    #[test]
+    pub fn test_no_repair() {
+        assert_eq!(
+            Err(RepairError::NotBroken { acc: 1 }),
+            repair(&generate("acc +1"))
+        );
+        let err = repair(&generate("nop +2\njmp -1\njmp +0")).unwrap_err();
+        assert_eq!(
+            "no single flip works, the program halts with infinite loop at 0; \
+             only these 0 addresses reach the end: []
+flipping 0 leads to 2
+flipping 1 leads to 2
+",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_overflow() {
+        let err = repair(&generate("nop +1\nacc +2147483647\nacc +1")).unwrap_err();
+        assert_eq!(
+            "no single flip works, the program halts with accumulator overflow at 2; \
+             only these 3 addresses reach the end: [0, 1, 2]
+flipping 0 halts with accumulator overflow at 2
+",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_repair_all() {
+        let program = generate("nop +2\njmp +0\njmp +0\nacc +5");
+        assert_eq!(
+            vec![Some(2), Some(2), Some(1), Some(0), Some(0)],
+            flip_distance(&program)
+        );
+        assert!(repair_all(&program, 1).is_empty());
+        let fixes: Vec<String> = repair_all(&program, 3).iter().map(Fix::to_string).collect();
+        assert_eq!(vec!["flip 0, 2: acc 5", "flip 1, 2: acc 5"], fixes);
+
+        assert_eq!(
+            vec![Fix {
+                addrs: vec![7],
+                acc: 8
+            }],
+            repair_all(&generate(EXAMPLE), 2)
+        );
+        assert_eq!(
+            vec![Fix {
+                addrs: vec![],
+                acc: 1
+            }],
+            repair_all(&generate("acc +1"), 2)
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+
+use super::Instruction;
+
+/// Why the machine stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    /// The instruction pointer reached the end of the program.
+    Terminated,
+    /// The instruction at `at` was about to run again, or the step limit was
+    /// reached there.
+    InfiniteLoop { at: usize },
+    /// A jump at the instruction pointer would leave the program.
+    OutOfBounds { target: i64 },
+    /// The `acc` at `at` would take the accumulator past the range of `i32`.
+    Overflow { at: usize },
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Halt::Terminated => write!(f, "terminated"),
+            Halt::InfiniteLoop { at } => write!(f, "infinite loop at {}", at),
+            Halt::OutOfBounds { target } => write!(f, "jump out of bounds to {}", target),
+            Halt::Overflow { at } => write!(f, "accumulator overflow at {}", at),
+        }
+    }
+}
+
+/// When to give up on a program that might not terminate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopDetection {
+    /// Stop before any instruction runs a second time.
+    Revisit,
+    /// Stop after this many steps.
+    StepLimit(usize),
+    /// Run until the program ends or jumps out.
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub ip: usize,
+    pub acc: i32,
+}
+
+/// A point in the execution of a `Vm` to rewind to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub registers: Registers,
+    pub steps: usize,
+}
+
+/// A recording machine keeps the registers from before every step, which is
+/// all it takes to run backwards. Others only count their steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vm {
+    pub registers: Registers,
+    pub detection: LoopDetection,
+    program: Vec<Instruction>,
+    visits: Vec<u32>,
+    steps: usize,
+    recording: bool,
+    history: Vec<Registers>,
+}
+
+impl Vm {
+    pub fn new(program: &[Instruction]) -> Vm {
+        Vm::with_detection(program, LoopDetection::Revisit)
+    }
+
+    pub fn with_detection(program: &[Instruction], detection: LoopDetection) -> Vm {
+        Vm {
+            registers: Registers::default(),
+            detection,
+            program: program.to_vec(),
+            visits: vec![0; program.len()],
+            steps: 0,
+            recording: false,
+            history: Vec::new(),
+        }
+    }
+
+    /// The machine, keeping a history of every step from now on so it can
+    /// step back, rewind and fork.
+    pub fn recording(mut self) -> Vm {
+        self.recording = true;
+        self
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    /// Replaces the instruction at `addr`, returning the old one.
+    pub fn patch(&mut self, addr: usize, instruction: Instruction) -> Instruction {
+        std::mem::replace(&mut self.program[addr], instruction)
+    }
+
+    /// Addresses executed since recording started, in order.
+    pub fn trace(&self) -> impl Iterator<Item = usize> + '_ {
+        self.history.iter().map(|registers| registers.ip)
+    }
+
+    /// Whether the instruction at `addr` ran on the way here.
+    pub fn visited(&self, addr: usize) -> bool {
+        self.visits[addr] > 0
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers,
+            steps: self.steps(),
+        }
+    }
+
+    /// Undoes the last recorded step, `false` if there is none.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(registers) => {
+                self.visits[registers.ip] -= 1;
+                self.registers = registers;
+                self.steps -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Undoes steps until the machine is back at `snapshot`, which must have
+    /// been taken on the way here while recording. Patches are not undone.
+    pub fn rewind(&mut self, snapshot: &Snapshot) {
+        assert!(
+            snapshot.steps <= self.steps(),
+            "snapshot is from the future"
+        );
+        assert!(
+            self.steps() - snapshot.steps <= self.history.len(),
+            "snapshot is from before recording"
+        );
+        while self.steps() > snapshot.steps {
+            self.step_back();
+        }
+        debug_assert_eq!(snapshot.registers, self.registers);
+    }
+
+    /// A copy of the machine rewound to `snapshot` with `instruction` at
+    /// `addr`, this machine stays as it is.
+    pub fn fork(&self, snapshot: &Snapshot, addr: usize, instruction: Instruction) -> Vm {
+        let mut fork = self.clone();
+        fork.rewind(snapshot);
+        fork.patch(addr, instruction);
+        fork
+    }
+
+    /// The reason the machine cannot run the current instruction, if any.
+    pub fn halted(&self) -> Option<Halt> {
+        let ip = self.registers.ip;
+        if ip == self.program.len() {
+            return Some(Halt::Terminated);
+        }
+        let looping = match self.detection {
+            LoopDetection::Revisit => self.visits[ip] > 0,
+            LoopDetection::StepLimit(limit) => self.steps() >= limit,
+            LoopDetection::Off => false,
+        };
+        if looping {
+            return Some(Halt::InfiniteLoop { at: ip });
+        }
+        let target = self.program[ip].target(ip);
+        if target < 0 || target > self.program.len() as i64 {
+            return Some(Halt::OutOfBounds { target });
+        }
+        if let Instruction::acc { val } = self.program[ip] {
+            if self.registers.acc.checked_add(val).is_none() {
+                return Some(Halt::Overflow { at: ip });
+            }
+        }
+        None
+    }
+
+    /// Runs one instruction. A halted machine stays where it is.
+    pub fn step(&mut self) -> Option<Halt> {
+        if let Some(halt) = self.halted() {
+            return Some(halt);
+        }
+        let ip = self.registers.ip;
+        if self.recording {
+            self.history.push(self.registers);
+        }
+        self.steps += 1;
+        self.visits[ip] += 1;
+        if let Instruction::acc { val } = self.program[ip] {
+            self.registers.acc += val;
+        }
+        self.registers.ip = self.program[ip].target(ip) as usize;
+        None
+    }
+
+    pub fn run(&mut self) -> Halt {
+        loop {
+            if let Some(halt) = self.step() {
+                return halt;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::day08::{generate, EXAMPLE};
+
+    #[test]
+    pub fn test_halts() {
+        let mut vm = Vm::new(&generate("nop +0\nacc +3\njmp -2")).recording();
+        assert_eq!(Halt::InfiniteLoop { at: 0 }, vm.run());
+        assert_eq!(Registers { ip: 0, acc: 3 }, vm.registers);
+        assert_eq!(vec![0, 1, 2], vm.trace().collect::<Vec<_>>());
+
+        let mut vm = Vm::new(&generate("acc +1\njmp -2"));
+        assert_eq!(Halt::OutOfBounds { target: -1 }, vm.run());
+        assert_eq!(Registers { ip: 1, acc: 1 }, vm.registers);
+
+        let mut vm = Vm::new(&generate("acc +1\njmp +2\nacc +5\nacc -2"));
+        assert_eq!(Halt::Terminated, vm.run());
+        assert_eq!(-1, vm.registers.acc);
+        assert_eq!(Some(Halt::Terminated), vm.step());
+
+        let mut vm = Vm::new(&generate("acc +2147483647\nacc +1"));
+        assert_eq!(Halt::Overflow { at: 1 }, vm.run());
+        assert_eq!(
+            Registers {
+                ip: 1,
+                acc: i32::MAX
+            },
+            vm.registers
+        );
+        assert_eq!(
+            "accumulator overflow at 1",
+            Halt::Overflow { at: 1 }.to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_detection() {
+        let program = generate("acc +1\njmp -1");
+        let mut vm = Vm::with_detection(&program, LoopDetection::StepLimit(7));
+        assert_eq!(Halt::InfiniteLoop { at: 1 }, vm.run());
+        assert_eq!(4, vm.registers.acc);
+
+        let mut vm = Vm::with_detection(&program, LoopDetection::Off);
+        for _ in 0..100 {
+            assert_eq!(None, vm.step());
+        }
+        assert_eq!(50, vm.registers.acc);
+        assert_eq!(100, vm.steps());
+        assert_eq!(0, vm.trace().count());
+        assert!(!vm.step_back());
+    }
+
+    #[test]
+    pub fn test_rewind() {
+        let program = generate(EXAMPLE);
+        let mut vm = Vm::new(&program).recording();
+        vm.step();
+        vm.step();
+        let checkpoint = vm.snapshot();
+        assert_eq!(Halt::InfiniteLoop { at: 1 }, vm.run());
+        assert!(vm.step_back());
+        assert_eq!(Registers { ip: 4, acc: 5 }, vm.registers);
+
+        let mut fork = vm.fork(&checkpoint, 7, Instruction::nop { val: -4 });
+        assert_eq!(Halt::Terminated, fork.run());
+        assert_eq!(8, fork.registers.acc);
+        assert_eq!(Instruction::jmp { val: -4 }, vm.program()[7]);
+
+        vm.rewind(&checkpoint);
+        assert_eq!(Registers { ip: 2, acc: 1 }, vm.registers);
+        assert_eq!(Halt::InfiniteLoop { at: 1 }, vm.run());
+        vm.rewind(&Vm::new(&program).snapshot());
+        assert_eq!(Registers::default(), vm.registers);
+        assert!(!vm.step_back());
+    }
+}
@@ -1,6 +1,34 @@
 extern crate aoc_runner_derive;
 extern crate aoc_runner;
 
-use aoc_runner_derive::aoc_main;
+use std::env;
+use std::process;
 
-aoc_main! { lib = advent_of_code_2020 }
\ No newline at end of file
+mod runner {
+    use aoc_runner_derive::aoc_main;
+
+    aoc_main! { lib = advent_of_code_2020 }
+
+    pub fn run() {
+        main()
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("bags") => advent_of_code_2020::day07::query::cli(&args[1..]),
+        Some("debug") => advent_of_code_2020::day08::debug::cli(&args[1..]),
+        _ => {
+            runner::run();
+            return;
+        }
+    };
+    match result {
+        Ok(out) => print!("{}", out),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}